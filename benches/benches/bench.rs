@@ -1,81 +1,47 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use advent_benches::registry::{self, Selection};
+use anyhow::bail;
+use criterion::Criterion;
 use std::hint::black_box;
 
-#[crabtime::function]
-fn gen_benches() {
-    // Find all solution modules
-    // Search the project root for crates that match the pattern y20*/src/day*/mod.rs
-    // Return a list of tuples containing the year and day of each solution module
-    let pattern = format!(
-        "{}/{}",
-        crabtime::WORKSPACE_PATH, "y20*/src/day*/mod.rs"
-    );
-    let mut years_and_days = glob::glob(&pattern)
-        .unwrap()
-        .filter_map(|entry| entry.ok())
-        .map(|path| {
-            let components = path
-                .components()
-                .filter_map(|c| c.as_os_str().to_str())
-                .map(|c| c.to_string())
-                .collect::<Vec<String>>();
-            let year = components[components.len() - 4].clone();
-            let day = components[components.len() - 2].clone();
-            (year, day)
-        })
-        .collect::<Vec<(String, String)>>();
-
-    // Sort the list so "day2" appears before "day10"
-    years_and_days.sort_by(|a, b| match a.1.len().cmp(&b.1.len()) {
-        std::cmp::Ordering::Equal => a.1.cmp(&b.1),
-        ordering => ordering,
-    });
-
-    // For every year and day ("y2024", "day3"), generate a benchmark function
-    // Store the benchmark function names in a vector and pass them to the criterion_group macro
-    let mut fn_names = Vec::new();
-    for (year, day) in years_and_days {
-        let input_file_path = &format!("{}/{}/src/{}/input.txt", crabtime::WORKSPACE_PATH, year, day);
-        let input_file = format!("{}_{}_INPUT", year, day);
-
-        let fn_name = format!("{}_{}", year, day);
-        fn_names.push(fn_name.clone());
-
-        let module_name = format!("{}::{}", year, day);
-
-        let test_str_one = format!("{} {} Part 1", year, day);
-        let test_str_two = format!("{} {} Part 2", year, day);
-
-        crabtime::output! {
-            static {{input_file}}: &str = include_str!(stringify!({{input_file_path}}));
-            fn {{fn_name}}(c: &mut Criterion) {
-                c.bench_function(
-                    stringify!({{test_str_one}}),
-                    |b| {
-                        b.iter(||
-                            {{module_name}}::part1(black_box({{input_file}}))
-                        );
-                    }
-                );
-                c.bench_function(
-                    stringify!({{test_str_two}}),
-                    |b| {
-                        b.iter(||
-                            {{module_name}}::part2(black_box({{input_file}}))
-                        );
-                    }
-                );
+// `cargo bench --bench bench -- --year 2024 --day 3 --part 1` benchmarks a single part.
+// `--input <file>` benchmarks one day against a different input file.
+// A bare argument is passed on to criterion as a regex filter on the benchmark name.
+fn main() -> anyhow::Result<()> {
+    let mut selection = Selection::default();
+    let mut criterion = Criterion::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if selection.parse_flag(&arg, &mut args)? {
+            continue;
+        }
+        match arg.as_str() {
+            // cargo passes this to every harness = false target
+            "--bench" => {}
+            "--save-baseline" | "--baseline" => {
+                let Some(name) = args.next() else {
+                    bail!("{arg} needs a name");
+                };
+                criterion = if arg == "--save-baseline" {
+                    criterion.save_baseline(name)
+                } else {
+                    criterion.retain_baseline(name, true)
+                };
             }
+            flag if flag.starts_with("--") => bail!("Unknown flag {flag}"),
+            filter => criterion = criterion.with_filter(filter),
         }
     }
-    let fn_names = fn_names.join(", ");
-    crabtime::output! {
-        criterion_group!(benches, {{fn_names}});
-        criterion_main!(benches);
+
+    for solution in selection.solutions()? {
+        let input = selection.input(solution)?;
+        for (part, runner) in selection.parts(solution) {
+            criterion.bench_function(&registry::bench_name(solution, part), |b| {
+                b.iter(|| (runner.run)(black_box(&input)))
+            });
+        }
     }
-}
 
-// I don't know why rust-analyzer says "Compilation of the generated code failed." when rustc compiles it. 
-// That's why I've added this to the settings.json -> "rust-analyzer.diagnostics.disabled": ["macro-error"]
-// Check back in a year if RA has fixed this.
-gen_benches!();
+    criterion.final_summary();
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use common::solution::Part;
+
+use crate::record::Allocations;
+
+/// Runs the part once under dhat and reports what it allocated.
+/// The binary calling this has to install `dhat::Alloc` as its global allocator,
+/// otherwise every count is zero.
+pub fn profile_part(part: &Part, input: &str) -> Allocations {
+    let _profiler = dhat::Profiler::builder().testing().build();
+    (part.run)(input);
+    let stats = dhat::HeapStats::get();
+    Allocations {
+        count: stats.total_blocks,
+        bytes: stats.total_bytes,
+        peak_bytes: stats.max_bytes as u64,
+    }
+}
@@ -1,52 +1,161 @@
 use anyhow::Result;
+use plotters::coord::ranged1d::{SegmentedCoord, ValueFormatter};
+use plotters::coord::types::RangedCoordu32;
+use plotters::coord::Shift;
 use plotters::prelude::*;
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 
-use crate::read::Record;
+use advent_benches::record::Record;
 
-#[allow(dead_code)]
-pub fn create_bar_chart(data: &BTreeMap<(u32, u32), Record>, year: u32) -> Result<()> {
-    let mut days = Vec::with_capacity(25);
-    let mut totals = Vec::with_capacity(25);
+const PART_ONE_COLOR: RGBColor = RGBColor(255, 127, 80);
+const PART_TWO_COLOR: RGBColor = RGBColor(70, 130, 180);
 
-    for record in data.values() {
-        if record.year != year {
-            continue;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Svg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scale {
+    Linear,
+    Log,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChartOptions {
+    pub format: Format,
+    pub scale: Scale,
+    pub output_dir: PathBuf,
+    pub size: (u32, u32),
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        ChartOptions {
+            format: Format::Png,
+            scale: Scale::Linear,
+            output_dir: PathBuf::from("benches/benches"),
+            size: (800, 600),
         }
-        days.push(record.day);
-        totals.push(record.total);
     }
+}
+
+impl ChartOptions {
+    pub fn output_path(&self, year: u32) -> PathBuf {
+        let extension = match self.format {
+            Format::Png => "png",
+            Format::Svg => "svg",
+        };
+        self.output_dir
+            .join(format!("advent_{year}_bar_chart.{extension}"))
+    }
+}
+
+struct Bar {
+    day: u32,
+    part_one: f64,
+    total: f64,
+}
+
+pub fn create_bar_chart(
+    data: &BTreeMap<(u32, u32), Record>,
+    year: u32,
+    options: &ChartOptions,
+) -> Result<()> {
+    let bars: Vec<Bar> = data
+        .values()
+        .filter(|record| record.year == year)
+        .map(|record| Bar {
+            day: record.day,
+            part_one: record.part_one_millis,
+            total: record.total,
+        })
+        .collect();
 
-    if days.is_empty() {
+    if bars.is_empty() {
         return Ok(());
     }
 
-    let total_time = totals.iter().sum::<f64>();
-    let max_total = totals.iter().cloned().fold(0.0_f64, f64::max);
-    let y_max = (max_total * 1.1).ceil();
+    let filename = options.output_path(year);
+    match options.format {
+        Format::Png => {
+            let root = BitMapBackend::new(&filename, options.size).into_drawing_area();
+            draw(root, &bars, year, options.scale)
+        }
+        Format::Svg => {
+            let root = SVGBackend::new(&filename, options.size).into_drawing_area();
+            draw(root, &bars, year, options.scale)
+        }
+    }
+}
 
-    let filename = format!("benches/benches/advent_{}_bar_chart.png", year);
-    let root = BitMapBackend::new(&filename, (800, 600)).into_drawing_area();
+fn draw<DB>(root: DrawingArea<DB, Shift>, bars: &[Bar], year: u32, scale: Scale) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
     root.fill(&WHITE)?;
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption(
-            format!("Time taken for each day in {} ({:.2}ms total)", year, total_time),
-            ("sans-serif", 24).into_font(),
-        )
+    let total_time = bars.iter().map(|bar| bar.total).sum::<f64>();
+    let max_total = bars.iter().map(|bar| bar.total).fold(0.0_f64, f64::max);
+    let caption = format!("Time taken for each day in {year} ({total_time:.2}ms total)");
+
+    let mut builder = ChartBuilder::on(&root);
+    builder
+        .caption(caption, ("sans-serif", 24).into_font())
         .margin(20)
         .x_label_area_size(40)
-        .y_label_area_size(60)
-        .build_cartesian_2d(
-            (1u32..25u32).into_segmented(),
-            0.0..y_max,
-        )?;
+        .y_label_area_size(60);
+    let x_range = (1u32..25u32).into_segmented();
 
+    match scale {
+        Scale::Linear => {
+            let y_max = (max_total * 1.1).ceil();
+            let mut chart = builder.build_cartesian_2d(x_range, 0.0..y_max)?;
+            draw_bars(&mut chart, bars, 0.0, "Time (ms)")?;
+        }
+        Scale::Log => {
+            // A log axis can't start at zero, so it starts at the power of 10 below the fastest part.
+            let min_positive = bars
+                .iter()
+                .flat_map(|bar| [bar.part_one, bar.total - bar.part_one])
+                .filter(|time| *time > 0.0)
+                .fold(f64::MAX, f64::min);
+            let y_min = if min_positive == f64::MAX {
+                0.001
+            } else {
+                10f64.powf(min_positive.log10().floor())
+            };
+            let y_max = 10f64.powf(max_total.max(y_min).log10().ceil());
+            let mut chart = builder.build_cartesian_2d(x_range, (y_min..y_max).log_scale())?;
+            draw_bars(&mut chart, bars, y_min, "Time (ms, log scale)")?;
+        }
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
+// Part 1 is drawn from the bottom of the axis, part 2 is stacked on top of it.
+fn draw_bars<'a, DB, Y>(
+    chart: &mut ChartContext<'a, DB, Cartesian2d<SegmentedCoord<RangedCoordu32>, Y>>,
+    bars: &[Bar],
+    baseline: f64,
+    y_desc: &str,
+) -> Result<()>
+where
+    DB: DrawingBackend + 'a,
+    DB::ErrorType: 'static,
+    Y: Ranged<ValueType = f64> + ValueFormatter<f64>,
+{
     chart
         .configure_mesh()
         .disable_mesh()
         .x_desc("Day")
-        .y_desc("Time (ms)")
+        .y_desc(y_desc)
         .x_labels(25)
         .x_label_formatter(&|x| {
             if let SegmentValue::CenterOf(v) = x {
@@ -59,16 +168,45 @@ pub fn create_bar_chart(data: &BTreeMap<(u32, u32), Record>, year: u32) -> Resul
         .label_style(("sans-serif", 12))
         .draw()?;
 
-    let bar_color = RGBColor(255, 127, 80);
+    let part_one: BTreeMap<u32, f64> = bars
+        .iter()
+        .map(|bar| (bar.day, bar.part_one.max(baseline)))
+        .collect();
 
-    chart.draw_series(
-        Histogram::vertical(&chart)
-            .style(bar_color.filled())
-            .margin(2)
-            .data(days.iter().zip(totals.iter()).map(|(&day, &total)| (day, total))),
-    )?;
+    chart
+        .draw_series(
+            Histogram::vertical(chart)
+                .style(PART_ONE_COLOR.filled())
+                .margin(2)
+                .baseline_func(|_| baseline)
+                .data(part_one.iter().map(|(&day, &time)| (day, time))),
+        )?
+        .label("Part 1")
+        .legend(|(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], PART_ONE_COLOR.filled()));
 
-    root.present()?;
+    chart
+        .draw_series(
+            Histogram::vertical(chart)
+                .style(PART_TWO_COLOR.filled())
+                .margin(2)
+                .baseline_func(|x| match x {
+                    SegmentValue::Exact(day) | SegmentValue::CenterOf(day) => {
+                        part_one.get(day).copied().unwrap_or(baseline)
+                    }
+                    SegmentValue::Last => baseline,
+                })
+                .data(bars.iter().map(|bar| (bar.day, bar.total.max(baseline)))),
+        )?
+        .label("Part 2")
+        .legend(|(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], PART_TWO_COLOR.filled()));
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.8))
+        .label_font(("sans-serif", 14))
+        .draw()?;
 
     Ok(())
 }
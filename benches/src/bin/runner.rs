@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use advent_benches::record::{self, Allocations, Record};
+use advent_benches::registry::{self, Selection};
+use advent_benches::timing;
+use anyhow::{anyhow, bail};
+use common::solution::Format;
+
+#[cfg(feature = "dhat-heap")]
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+// `cargo run --release --bin runner -- --year 2024 --day 3` prints each answer and how long it took.
+// `--iterations <n>` sets how many timed runs each part gets. `--input <file>` works as in the benches.
+// `--csv <file>` updates the rows the README generator reads with the times from this run.
+// `--alloc` reports allocations instead of times. It needs `--features dhat-heap`.
+// `--explain` prints how the answer was reached instead, for days that can describe it.
+// `--explain-json` does the same as JSON.
+fn main() -> anyhow::Result<()> {
+    let mut selection = Selection::default();
+    let mut iterations = 50;
+    let mut csv_path = None;
+    let mut profile_allocations = false;
+    let mut explain = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if selection.parse_flag(&arg, &mut args)? {
+            continue;
+        }
+        let mut value = || args.next().ok_or_else(|| anyhow!("{arg} needs a value"));
+        match arg.as_str() {
+            "--iterations" => iterations = value()?.parse()?,
+            "--csv" => csv_path = Some(PathBuf::from(value()?)),
+            "--alloc" => profile_allocations = true,
+            "--explain" => explain = Some(Format::Text),
+            "--explain-json" => explain = Some(Format::Json),
+            _ => bail!("Unknown argument {arg}"),
+        }
+    }
+
+    if profile_allocations && !cfg!(feature = "dhat-heap") {
+        bail!("--alloc needs the runner to be built with `--features dhat-heap`");
+    }
+
+    let mut records: BTreeMap<(u32, u32), Record> = match &csv_path {
+        Some(csv_path) => record::read_csv(csv_path)?
+            .into_iter()
+            .map(|record| ((record.year, record.day), record))
+            .collect(),
+        None => BTreeMap::new(),
+    };
+
+    for solution in selection.solutions()? {
+        let input = selection.input(solution)?;
+        let record = records
+            .entry((solution.year, solution.day))
+            .or_insert_with(|| Record::new(solution.year, solution.day));
+        for (part, runner) in selection.parts(solution) {
+            let name = registry::bench_name(solution, part);
+            let answer = (runner.answer)(&input);
+            if let Some(format) = explain {
+                println!("{name:<20} {answer:>20}");
+                match runner.explain {
+                    Some(explain) => println!("{}", explain(&input, format)),
+                    None => println!("No explanation for this part"),
+                }
+            } else if profile_allocations {
+                let allocations = profile_part(runner, &input);
+                println!(
+                    "{name:<20} {answer:>20}   {:>9} allocations   {:>12} bytes   {:>12} bytes peak",
+                    allocations.count, allocations.bytes, allocations.peak_bytes,
+                );
+                record.set_allocations(part, Some(allocations));
+            } else {
+                let timing = timing::time_part(runner, &input, iterations);
+                println!(
+                    "{name:<20} {answer:>20}   min {:>10.2?}   median {:>10.2?}   mean {:>10.2?}   ({} of {} runs rejected)",
+                    timing.min,
+                    timing.median,
+                    timing.mean,
+                    timing.outliers,
+                    timing.iterations,
+                );
+                record.set_millis(part, timing::millis(&timing));
+            }
+        }
+    }
+
+    if let Some(csv_path) = csv_path {
+        record::write_to_csv(&csv_path, &records)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "dhat-heap")]
+fn profile_part(part: &common::solution::Part, input: &str) -> Allocations {
+    advent_benches::allocations::profile_part(part, input)
+}
+
+#[cfg(not(feature = "dhat-heap"))]
+fn profile_part(_part: &common::solution::Part, _input: &str) -> Allocations {
+    unreachable!("checked when parsing --alloc")
+}
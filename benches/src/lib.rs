@@ -0,0 +1,5 @@
+#[cfg(feature = "dhat-heap")]
+pub mod allocations;
+pub mod record;
+pub mod registry;
+pub mod timing;
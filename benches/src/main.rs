@@ -1,19 +1,51 @@
 use std::path::PathBuf;
 
+use advent_benches::record;
+use anyhow::bail;
+use bar_chart::{ChartOptions, Format, Scale};
+
 mod bar_chart;
 mod read;
+mod titles;
 mod write;
 
 fn main() -> anyhow::Result<()> {
+    let chart_options = parse_chart_options(std::env::args().skip(1))?;
+
     // Read all files that match the glob patter /target/criterion/*/new/estimates.json
     let csv_path = PathBuf::from("benches/data.csv");
+    let titles_path = PathBuf::from("benches/titles.csv");
 
     let records = read::read_all_data(&csv_path)?;
 
-    write::write_to_readme(&records)?;
-    write::write_to_csv(&csv_path, &records)?;
+    let titles = titles::read_all_titles(&titles_path)?;
+    titles::write_titles(&titles_path, &titles)?;
+
+    write::write_to_readme(&records, &titles)?;
+    record::write_to_csv(&csv_path, &records)?;
 
-    bar_chart::create_bar_chart(&records, 2024)?;
+    bar_chart::create_bar_chart(&records, 2024, &chart_options)?;
 
     Ok(())
 }
+
+// Accepts `--svg`, `--log-scale` and `--chart-dir <dir>`. Other positional arguments are ignored.
+fn parse_chart_options(mut args: impl Iterator<Item = String>) -> anyhow::Result<ChartOptions> {
+    let mut options = ChartOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--svg" => options.format = Format::Svg,
+            "--png" => options.format = Format::Png,
+            "--log-scale" => options.scale = Scale::Log,
+            "--chart-dir" => {
+                let Some(dir) = args.next() else {
+                    bail!("--chart-dir needs a directory");
+                };
+                options.output_dir = PathBuf::from(dir);
+            }
+            flag if flag.starts_with("--") => bail!("Unknown flag {flag}"),
+            _ => {}
+        }
+    }
+    Ok(options)
+}
@@ -1,44 +1,36 @@
+use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
 use std::path::Path;
 use std::{fs::File, io::BufReader};
 
+use advent_benches::record::{self, Record};
 use anyhow::Result;
 use scan_fmt::scan_fmt;
-use serde::{Deserialize, Serialize};
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Record {
-    pub year: u32,
-    pub day: u32,
-    pub part_one_millis: f64,
-    pub part_two_millis: f64,
-    pub total: f64,
-}
+use serde::Deserialize;
 
 // Read the benches in the target folder and the csv file, if any.
 // If a record exists in the csv file but not in the benches, add it.
+// Criterion doesn't measure allocations, so those always come from the csv file.
 pub fn read_all_data(csv_file: &Path) -> Result<BTreeMap<(u32, u32), Record>> {
     let mut benches = read_benches()?;
-    let csv_records = read_csv(csv_file)?;
+    let csv_records = record::read_csv(csv_file)?;
     for record in csv_records {
-        benches.entry((record.year, record.day)).or_insert(record);
+        match benches.entry((record.year, record.day)) {
+            Entry::Vacant(entry) => {
+                entry.insert(record);
+            }
+            Entry::Occupied(mut entry) => {
+                for part in [1, 2] {
+                    entry
+                        .get_mut()
+                        .set_allocations(part, record.allocations(part));
+                }
+            }
+        }
     }
     Ok(benches)
 }
 
-fn read_csv(csv_file: &Path) -> Result<Vec<Record>> {
-    if !csv_file.exists() {
-        return Ok(Vec::new());
-    }
-
-    let file = File::open(csv_file)?;
-    let reader: BufReader<File> = BufReader::new(file);
-    csv::Reader::from_reader(reader)
-        .deserialize()
-        .collect::<Result<Vec<Record>, csv::Error>>()
-        .map_err(|_| anyhow::anyhow!("Failed to read CSV"))
-}
-
 fn read_benches() -> Result<BTreeMap<(u32, u32), Record>> {
     let pattern = "target/criterion/*/new/estimates.json";
     let benches = glob::glob(pattern)?
@@ -52,20 +44,9 @@ fn read_benches() -> Result<BTreeMap<(u32, u32), Record>> {
             Some((year, day, part, bench.mean.point_estimate))
         })
         .fold(BTreeMap::new(), |mut acc, (year, day, part, time)| {
-            let record = acc.entry((year, day)).or_insert(Record {
-                year,
-                day,
-                part_one_millis: 0.0,
-                part_two_millis: 0.0,
-                total: 0.0,
-            });
-
-            match part {
-                1 => record.part_one_millis = time / 1000000.0,
-                2 => record.part_two_millis = time / 1000000.0,
-                _ => {}
-            }
-            record.total = record.part_one_millis + record.part_two_millis;
+            acc.entry((year, day))
+                .or_insert_with(|| Record::new(year, day))
+                .set_millis(part, time / 1000000.0);
             acc
         });
 
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs::File, io::BufReader};
+
+use serde::{Deserialize, Serialize};
+
+/// One row of `benches/data.csv`, which the README and bar chart are generated from.
+/// The allocation columns are only filled in by the runner's profiling mode.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Record {
+    pub year: u32,
+    pub day: u32,
+    pub part_one_millis: f64,
+    pub part_two_millis: f64,
+    pub total: f64,
+    pub part_one_allocations: Option<u64>,
+    pub part_one_allocated_bytes: Option<u64>,
+    pub part_one_peak_bytes: Option<u64>,
+    pub part_two_allocations: Option<u64>,
+    pub part_two_allocated_bytes: Option<u64>,
+    pub part_two_peak_bytes: Option<u64>,
+}
+
+/// Heap usage of a single run of one part.
+#[derive(Debug, Clone, Copy)]
+pub struct Allocations {
+    pub count: u64,
+    pub bytes: u64,
+    pub peak_bytes: u64,
+}
+
+impl Record {
+    pub fn new(year: u32, day: u32) -> Record {
+        Record {
+            year,
+            day,
+            part_one_millis: 0.0,
+            part_two_millis: 0.0,
+            total: 0.0,
+            part_one_allocations: None,
+            part_one_allocated_bytes: None,
+            part_one_peak_bytes: None,
+            part_two_allocations: None,
+            part_two_allocated_bytes: None,
+            part_two_peak_bytes: None,
+        }
+    }
+
+    pub fn set_millis(&mut self, part: u32, millis: f64) {
+        match part {
+            1 => self.part_one_millis = millis,
+            2 => self.part_two_millis = millis,
+            _ => {}
+        }
+        self.total = self.part_one_millis + self.part_two_millis;
+    }
+
+    pub fn allocations(&self, part: u32) -> Option<Allocations> {
+        let (count, bytes, peak_bytes) = match part {
+            1 => (
+                self.part_one_allocations,
+                self.part_one_allocated_bytes,
+                self.part_one_peak_bytes,
+            ),
+            2 => (
+                self.part_two_allocations,
+                self.part_two_allocated_bytes,
+                self.part_two_peak_bytes,
+            ),
+            _ => return None,
+        };
+        Some(Allocations {
+            count: count?,
+            bytes: bytes?,
+            peak_bytes: peak_bytes?,
+        })
+    }
+
+    pub fn set_allocations(&mut self, part: u32, allocations: Option<Allocations>) {
+        let count = allocations.map(|a| a.count);
+        let bytes = allocations.map(|a| a.bytes);
+        let peak_bytes = allocations.map(|a| a.peak_bytes);
+        match part {
+            1 => {
+                self.part_one_allocations = count;
+                self.part_one_allocated_bytes = bytes;
+                self.part_one_peak_bytes = peak_bytes;
+            }
+            2 => {
+                self.part_two_allocations = count;
+                self.part_two_allocated_bytes = bytes;
+                self.part_two_peak_bytes = peak_bytes;
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn read_csv(csv_file: &Path) -> anyhow::Result<Vec<Record>> {
+    if !csv_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(csv_file)?;
+    let reader: BufReader<File> = BufReader::new(file);
+    csv::Reader::from_reader(reader)
+        .deserialize()
+        .collect::<Result<Vec<Record>, csv::Error>>()
+        .map_err(|_| anyhow::anyhow!("Failed to read CSV"))
+}
+
+pub fn write_to_csv(csv_file: &Path, data: &BTreeMap<(u32, u32), Record>) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(csv_file)?;
+    for record in data.values() {
+        writer.serialize(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A file in the temp directory that's deleted when the test ends.
+    struct TempCsv(std::path::PathBuf);
+
+    impl TempCsv {
+        fn new(name: &str) -> TempCsv {
+            let file = format!("advent-benches-{}-{name}.csv", std::process::id());
+            TempCsv(std::env::temp_dir().join(file))
+        }
+    }
+
+    impl Drop for TempCsv {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn reads_rows_without_allocation_columns() {
+        let csv = TempCsv::new("old");
+        std::fs::write(
+            &csv.0,
+            "year,day,part_one_millis,part_two_millis,total\n2024,1,0.5,1.5,2.0\n",
+        )
+        .unwrap();
+
+        let records = read_csv(&csv.0).unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!((record.year, record.day), (2024, 1));
+        assert_eq!(record.part_one_millis, 0.5);
+        assert_eq!(record.part_two_millis, 1.5);
+        assert_eq!(record.total, 2.0);
+        assert!(record.allocations(1).is_none());
+        assert!(record.allocations(2).is_none());
+    }
+
+    #[test]
+    fn round_trips_allocations() {
+        let mut with_allocations = Record::new(2024, 3);
+        with_allocations.set_millis(1, 0.25);
+        with_allocations.set_millis(2, 0.75);
+        with_allocations.set_allocations(
+            2,
+            Some(Allocations {
+                count: 12,
+                bytes: 3456,
+                peak_bytes: 789,
+            }),
+        );
+        let mut data = BTreeMap::new();
+        data.insert((2024, 3), with_allocations);
+        data.insert((2023, 1), Record::new(2023, 1));
+
+        let csv = TempCsv::new("round-trip");
+        write_to_csv(&csv.0, &data).unwrap();
+        let records = read_csv(&csv.0).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].year, records[0].day), (2023, 1));
+        assert!(records[0].allocations(2).is_none());
+
+        let record = &records[1];
+        assert_eq!(record.total, 1.0);
+        assert!(record.allocations(1).is_none());
+        let allocations = record.allocations(2).unwrap();
+        assert_eq!(
+            (allocations.count, allocations.bytes, allocations.peak_bytes),
+            (12, 3456, 789)
+        );
+
+        // Clearing them writes empty columns, which read back as nothing
+        let mut data: BTreeMap<_, _> = records.into_iter().map(|r| ((r.year, r.day), r)).collect();
+        data.get_mut(&(2024, 3)).unwrap().set_allocations(2, None);
+        write_to_csv(&csv.0, &data).unwrap();
+        assert!(read_csv(&csv.0).unwrap()[1].allocations(2).is_none());
+    }
+
+    #[test]
+    fn missing_file_has_no_records() {
+        let csv = TempCsv::new("missing");
+        assert!(read_csv(&csv.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_rows() {
+        let csv = TempCsv::new("malformed");
+        std::fs::write(
+            &csv.0,
+            "year,day,part_one_millis,part_two_millis,total\n2024,one,0.5,1.5,2.0\n",
+        )
+        .unwrap();
+        assert!(read_csv(&csv.0).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use common::solution::{Part, Solution};
+
+pub fn all_solutions() -> impl Iterator<Item = &'static Solution> {
+    y2022::SOLUTIONS
+        .iter()
+        .chain(y2023::SOLUTIONS)
+        .chain(y2024::SOLUTIONS)
+        .chain(y2025::SOLUTIONS)
+}
+
+pub fn workspace_root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("benches is inside the workspace")
+}
+
+/// Which solutions to run, and optionally a different input file to run them against.
+#[derive(Debug, Default)]
+pub struct Selection {
+    pub year: Option<u32>,
+    pub day: Option<u32>,
+    pub part: Option<u32>,
+    pub input: Option<PathBuf>,
+}
+
+impl Selection {
+    /// Handles `--year`, `--day`, `--part` and `--input`, reading the value from `args`.
+    /// Returns false if `flag` isn't one of these, so the caller can handle it.
+    pub fn parse_flag(
+        &mut self,
+        flag: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<bool> {
+        let mut value = || args.next().ok_or_else(|| anyhow!("{flag} needs a value"));
+        match flag {
+            "--year" => self.year = Some(value()?.parse()?),
+            "--day" => self.day = Some(value()?.parse()?),
+            "--part" => self.part = Some(value()?.parse()?),
+            "--input" => self.input = Some(PathBuf::from(value()?)),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn solutions(&self) -> Result<Vec<&'static Solution>> {
+        let solutions: Vec<_> = all_solutions()
+            .filter(|s| self.year.is_none_or(|year| s.year == year))
+            .filter(|s| self.day.is_none_or(|day| s.day == day))
+            .collect();
+        if self.input.is_some() && solutions.len() != 1 {
+            bail!("--input needs --year and --day to select exactly one solution");
+        }
+        Ok(solutions)
+    }
+
+    pub fn parts<'a>(&self, solution: &'a Solution) -> impl Iterator<Item = (u32, &'a Part)> {
+        let selected = self.part;
+        (1..=2)
+            .filter(move |part| selected.is_none_or(|selected| selected == *part))
+            .filter_map(|part| Some((part, solution.part(part)?)))
+    }
+
+    pub fn input(&self, solution: &Solution) -> Result<String> {
+        let path = match &self.input {
+            Some(path) => path.clone(),
+            None => workspace_root().join(solution.input_path()),
+        };
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))
+    }
+}
+
+/// The name a part is benchmarked under, e.g. "y2024 day3 Part 1".
+/// `read::read_benches` parses these names back out of criterion's output.
+pub fn bench_name(solution: &Solution, part: u32) -> String {
+    format!("y{} day{} Part {}", solution.year, solution.day, part)
+}
@@ -0,0 +1,115 @@
+use std::time::{Duration, Instant};
+
+use common::solution::Part;
+
+/// Summary of running one part many times. Outliers are dropped before taking the mean.
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    pub iterations: usize,
+    pub outliers: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub mean: Duration,
+}
+
+/// Runs the part once to warm up, then `iterations` times while timing each run.
+pub fn time_part(part: &Part, input: &str, iterations: usize) -> Timing {
+    (part.run)(input);
+    let samples = (0..iterations.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            (part.run)(input);
+            start.elapsed()
+        })
+        .collect();
+    Timing::from_samples(samples)
+}
+
+impl Timing {
+    // Samples outside Tukey's fences (1.5 IQR beyond the quartiles) are rejected.
+    // Median and min are taken over all samples since outliers barely move them.
+    fn from_samples(mut samples: Vec<Duration>) -> Timing {
+        samples.sort_unstable();
+        let iterations = samples.len();
+        let quartile = |q: usize| samples[(iterations - 1) * q / 4];
+        let (q1, q3) = (quartile(1), quartile(3));
+        let fence = (q3 - q1).mul_f64(1.5);
+        let (low, high) = (q1.saturating_sub(fence), q3 + fence);
+
+        let kept: Vec<Duration> = samples
+            .iter()
+            .copied()
+            .filter(|sample| (low..=high).contains(sample))
+            .collect();
+        let mean = kept.iter().sum::<Duration>() / kept.len() as u32;
+
+        Timing {
+            iterations,
+            outliers: iterations - kept.len(),
+            min: samples[0],
+            median: quartile(2),
+            mean,
+        }
+    }
+}
+
+/// Milliseconds as stored in a `Record`, using the mean since that's what criterion reports.
+pub fn millis(timing: &Timing) -> f64 {
+    timing.mean.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn micros(samples: &[u64]) -> Vec<Duration> {
+        samples.iter().map(|m| Duration::from_micros(*m)).collect()
+    }
+
+    #[test]
+    fn identical_samples() {
+        let timing = Timing::from_samples(micros(&[100; 10]));
+        assert_eq!(timing.iterations, 10);
+        assert_eq!(timing.outliers, 0);
+        assert_eq!(timing.min, Duration::from_micros(100));
+        assert_eq!(timing.median, Duration::from_micros(100));
+        assert_eq!(timing.mean, Duration::from_micros(100));
+    }
+
+    #[test]
+    fn single_sample() {
+        let timing = Timing::from_samples(micros(&[42]));
+        assert_eq!(timing.iterations, 1);
+        assert_eq!(timing.outliers, 0);
+        assert_eq!(timing.mean, Duration::from_micros(42));
+    }
+
+    #[test]
+    fn rejects_slow_outliers() {
+        // Quartiles are 102 and 108, so the fences are at 93 and 117
+        let timing = Timing::from_samples(micros(&[104, 5000, 100, 108, 102, 120, 104, 100, 106]));
+        assert_eq!(timing.iterations, 9);
+        assert_eq!(timing.outliers, 2);
+        assert_eq!(timing.min, Duration::from_micros(100));
+        assert_eq!(timing.median, Duration::from_micros(104));
+        // 724µs over the 7 samples kept
+        assert_eq!(timing.mean, Duration::from_nanos(103_428));
+    }
+
+    #[test]
+    fn rejects_fast_outliers() {
+        let timing = Timing::from_samples(micros(&[1, 100, 101, 102, 103, 104, 105, 106, 107]));
+        assert_eq!(timing.outliers, 1);
+        // The minimum is still over every sample
+        assert_eq!(timing.min, Duration::from_micros(1));
+        assert_eq!(timing.mean, Duration::from_nanos(103_500));
+    }
+
+    #[test]
+    fn keeps_everything_when_spread_evenly() {
+        let samples: Vec<u64> = (1..=20).map(|i| i * 10).collect();
+        let timing = Timing::from_samples(micros(&samples));
+        assert_eq!(timing.outliers, 0);
+        assert_eq!(timing.mean, Duration::from_micros(105));
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use regex::Regex;
+use scan_fmt::scan_fmt;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Title {
+    year: u32,
+    day: u32,
+    title: String,
+}
+
+// Read the cached titles, then add any title that can be found in a checked-in Readme.
+// Days whose puzzle text isn't checked in keep whatever title was cached for them.
+pub fn read_all_titles(titles_file: &Path) -> Result<BTreeMap<(u32, u32), String>> {
+    let mut titles = read_titles(titles_file)?;
+    titles.extend(read_readmes()?);
+    Ok(titles)
+}
+
+pub fn write_titles(titles_file: &Path, titles: &BTreeMap<(u32, u32), String>) -> Result<()> {
+    let mut writer = csv::Writer::from_path(titles_file)?;
+    for (&(year, day), title) in titles {
+        writer.serialize(Title {
+            year,
+            day,
+            title: title.clone(),
+        })?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_titles(titles_file: &Path) -> Result<BTreeMap<(u32, u32), String>> {
+    if !titles_file.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    csv::Reader::from_path(titles_file)?
+        .deserialize()
+        .map(|title| title.map(|t: Title| ((t.year, t.day), t.title)))
+        .collect::<Result<BTreeMap<_, _>, csv::Error>>()
+        .map_err(|_| anyhow::anyhow!("Failed to read titles CSV"))
+}
+
+fn read_readmes() -> Result<BTreeMap<(u32, u32), String>> {
+    let re = Regex::new(r"-- Day [0-9]+: (.*) --")?;
+    let titles = glob::glob("y20*/src/day*/Readme.md")?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|path| {
+            let path_str = path.to_str()?;
+            let (year, day) = scan_fmt!(path_str, "y{d}/src/day{d}/Readme.md", u32, u32).ok()?;
+            let content = std::fs::read_to_string(&path).ok()?;
+            let title = re.captures(&content)?.get(1)?.as_str().to_string();
+            Some(((year, day), title))
+        })
+        .collect();
+    Ok(titles)
+}
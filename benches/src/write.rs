@@ -1,23 +1,17 @@
-use std::{collections::BTreeMap, path::Path};
+use std::collections::BTreeMap;
 
-use crate::read::Record;
+use advent_benches::record::{Allocations, Record};
 
 const README_TEMPLATE: &str = include_str!("readme.tmpl");
 
-pub fn write_to_csv(csv_file: &Path, data: &BTreeMap<(u32, u32), Record>) -> anyhow::Result<()> {
-    let mut writer = csv::Writer::from_path(csv_file)?;
-    for record in data.values() {
-        writer.serialize(record)?;
-    }
-    writer.flush()?;
-    Ok(())
-}
-
-pub fn write_to_readme(data: &BTreeMap<(u32, u32), Record>) -> anyhow::Result<()> {
+pub fn write_to_readme(
+    data: &BTreeMap<(u32, u32), Record>,
+    titles: &BTreeMap<(u32, u32), String>,
+) -> anyhow::Result<()> {
     let mut output = String::new();
     #[allow(clippy::single_element_loop)]
     for year in [2024] {
-        output.push_str(&markdown_for_year(data, year));
+        output.push_str(&markdown_for_year(data, titles, year));
     }
 
     let readme = README_TEMPLATE.replace("{{table}}", &output);
@@ -26,13 +20,29 @@ pub fn write_to_readme(data: &BTreeMap<(u32, u32), Record>) -> anyhow::Result<()
     Ok(())
 }
 
-fn markdown_for_year(data: &BTreeMap<(u32, u32), Record>, required_year: u32) -> String {
+fn markdown_for_year(
+    data: &BTreeMap<(u32, u32), Record>,
+    titles: &BTreeMap<(u32, u32), String>,
+    required_year: u32,
+) -> String {
+    // Allocation columns only show up once the runner's profiling mode has filled them in.
+    let show_allocations = data
+        .values()
+        .any(|record| record.year == required_year && record.allocations(1).is_some());
+
     let mut output = format!(
-        "### {required_year}\n\n| Day  | Problem     | Solution    | Part 1 (ms) | Part 2 (ms) | Total (ms) |\n",
+        "### {required_year}\n\n| Day  | Problem     | Solution    | Part 1 (ms) | Part 2 (ms) | Total (ms) |",
     );
+    if show_allocations {
+        output.push_str(" Allocations | Peak heap (KB) |");
+    }
     output.push_str(
-        "|------|-------------|-------------|-------------|-------------|------------|\n",
+        "\n|------|-------------|-------------|-------------|-------------|------------|",
     );
+    if show_allocations {
+        output.push_str("-------------|----------------|");
+    }
+    output.push('\n');
     let mut part_one_total = 0.0;
     let mut part_two_total = 0.0;
     let mut total_total = 0.0;
@@ -42,30 +52,45 @@ fn markdown_for_year(data: &BTreeMap<(u32, u32), Record>, required_year: u32) ->
         }
 
         let url = format!("https://adventofcode.com/{}/day/{}", year, day);
-        let problem_name =
-            get_problem_name(*year, *day).unwrap_or_else(|| "Unknown problem name".to_string());
+        let problem_name = titles
+            .get(&(*year, *day))
+            .map_or("Unknown problem name", String::as_str);
         let solution_url = format!("/y{year}/src/day{day}/mod.rs");
         output.push_str(&format!(
-            "| {day} | [{problem_name}]({url}) | [Solution]({solution_url}) | {:.2} | {:.2} | {:.2} |\n",
+            "| {day} | [{problem_name}]({url}) | [Solution]({solution_url}) | {:.2} | {:.2} | {:.2} |",
             record.part_one_millis, record.part_two_millis, record.total
         ));
+        if show_allocations {
+            output.push_str(&allocation_columns(record));
+        }
+        output.push('\n');
         part_one_total += record.part_one_millis;
         part_two_total += record.part_two_millis;
         total_total += record.total;
     }
 
     output.push_str(&format!(
-        "|  |  | Total | {:.2}ms | {:.2}ms | {:.2}ms |\n\n",
+        "|  |  | Total | {:.2}ms | {:.2}ms | {:.2}ms |",
         part_one_total, part_two_total, total_total
     ));
+    if show_allocations {
+        output.push_str("  |  |");
+    }
+    output.push_str("\n\n");
 
     output
 }
 
-fn get_problem_name(year: u32, day: u32) -> Option<String> {
-    let path = format!("y{year}/src/day{day}/Readme.md");
-    let content = std::fs::read_to_string(path).unwrap();
-    let re = regex::Regex::new(r"-- Day [0-9]+: (.*) --").unwrap();
-    let problem_name = re.captures(&content)?.get(1)?.as_str().to_string();
-    Some(problem_name)
+// Each cell is "part 1 / part 2".
+fn allocation_columns(record: &Record) -> String {
+    let [one, two] = [1, 2].map(|part| record.allocations(part));
+    let cell = |f: fn(&Allocations) -> String| {
+        let [one, two] = [one, two].map(|a| a.as_ref().map_or("-".to_string(), f));
+        format!("{one} / {two}")
+    };
+    format!(
+        " {} | {} |",
+        cell(|a| a.count.to_string()),
+        cell(|a| format!("{:.1}", a.peak_bytes as f64 / 1024.0))
+    )
 }
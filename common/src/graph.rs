@@ -0,0 +1,485 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Write;
+
+/// Gives every distinct name an id, counting up from 0 in the order the names are first seen.
+#[derive(Debug, Clone, Default)]
+pub struct Interner<'a> {
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, usize>,
+}
+
+impl<'a> Interner<'a> {
+    pub fn new() -> Interner<'a> {
+        Interner::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Interner<'a> {
+        Interner {
+            names: Vec::with_capacity(capacity),
+            ids: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn intern(&mut self, name: &'a str) -> usize {
+        *self.ids.entry(name).or_insert_with(|| {
+            self.names.push(name);
+            self.names.len() - 1
+        })
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: usize) -> &'a str {
+        self.names[id]
+    }
+
+    /// Every name, indexed by id.
+    pub fn names(&self) -> &[&'a str] {
+        &self.names
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Vertices on a cycle, each with an edge to the next and the last with an edge to the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle(pub Vec<usize>);
+
+/// A directed graph with vertices `0..len()`, storing the out-neighbours of each vertex.
+/// Undirected graphs are stored with an edge in each direction.
+#[derive(Debug, Clone, Default)]
+pub struct AdjacencyList {
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl AdjacencyList {
+    pub fn new(len: usize) -> AdjacencyList {
+        AdjacencyList {
+            neighbours: vec![Vec::new(); len],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbours.is_empty()
+    }
+
+    pub fn add_vertex(&mut self) -> usize {
+        self.neighbours.push(Vec::new());
+        self.neighbours.len() - 1
+    }
+
+    /// Adds vertices as needed so that both ends exist.
+    pub fn add_edge(&mut self, from: usize, to: usize) {
+        let len = self.len().max(from + 1).max(to + 1);
+        self.neighbours.resize_with(len, Vec::new);
+        self.neighbours[from].push(to);
+    }
+
+    pub fn add_undirected_edge(&mut self, one: usize, two: usize) {
+        self.add_edge(one, two);
+        self.add_edge(two, one);
+    }
+
+    pub fn neighbours(&self, vertex: usize) -> &[usize] {
+        &self.neighbours[vertex]
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.neighbours
+            .iter()
+            .enumerate()
+            .flat_map(|(from, neighbours)| neighbours.iter().map(move |to| (from, *to)))
+    }
+
+    /// The same graph with every edge pointing the other way.
+    pub fn reversed(&self) -> AdjacencyList {
+        let mut reversed = AdjacencyList::new(self.len());
+        for (from, to) in self.edges() {
+            reversed.neighbours[to].push(from);
+        }
+        reversed
+    }
+
+    /// Kahn's algorithm. Ties are broken by the smaller vertex, so the order is deterministic.
+    /// If some vertices can't be ordered, returns one of the cycles that's in the way.
+    pub fn topological_sort(&self) -> Result<Vec<usize>, Cycle> {
+        let mut in_degrees = vec![0; self.len()];
+        for (_, to) in self.edges() {
+            in_degrees[to] += 1;
+        }
+        let mut ready: BinaryHeap<_> = (0..self.len())
+            .filter(|vertex| in_degrees[*vertex] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(Reverse(vertex)) = ready.pop() {
+            order.push(vertex);
+            for &neighbour in &self.neighbours[vertex] {
+                in_degrees[neighbour] -= 1;
+                if in_degrees[neighbour] == 0 {
+                    ready.push(Reverse(neighbour));
+                }
+            }
+        }
+
+        if order.len() == self.len() {
+            return Ok(order);
+        }
+        // Every vertex left over has an edge from another leftover vertex.
+        // Walking those edges backwards has to come back around to a vertex already seen.
+        let reversed = self.reversed();
+        let mut vertex = (0..self.len())
+            .find(|vertex| in_degrees[*vertex] > 0)
+            .expect("some vertex wasn't ordered");
+        let mut path = Vec::new();
+        while !path.contains(&vertex) {
+            path.push(vertex);
+            vertex = *reversed.neighbours[vertex]
+                .iter()
+                .find(|predecessor| in_degrees[**predecessor] > 0)
+                .expect("a leftover vertex has a leftover predecessor");
+        }
+        let start = path.iter().position(|v| *v == vertex).unwrap();
+        Err(Cycle(path[start..].iter().rev().copied().collect()))
+    }
+
+    /// Tarjan's algorithm, with an explicit stack so deep graphs don't overflow.
+    /// Components come out in reverse topological order: no edge leads to a later component.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        const UNVISITED: usize = usize::MAX;
+        let mut index = vec![UNVISITED; self.len()];
+        let mut low_link = vec![0; self.len()];
+        let mut on_stack = vec![false; self.len()];
+        let mut stack = Vec::new();
+        let mut next_index = 0;
+        let mut components = Vec::new();
+
+        for root in 0..self.len() {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            // (vertex, how many of its neighbours have been looked at)
+            let mut work = vec![(root, 0)];
+            while let Some(&(vertex, next)) = work.last() {
+                if next == 0 && index[vertex] == UNVISITED {
+                    index[vertex] = next_index;
+                    low_link[vertex] = next_index;
+                    next_index += 1;
+                    stack.push(vertex);
+                    on_stack[vertex] = true;
+                }
+
+                if let Some(&neighbour) = self.neighbours[vertex].get(next) {
+                    work.last_mut().unwrap().1 += 1;
+                    if index[neighbour] == UNVISITED {
+                        work.push((neighbour, 0));
+                    } else if on_stack[neighbour] {
+                        low_link[vertex] = low_link[vertex].min(index[neighbour]);
+                    }
+                    continue;
+                }
+
+                work.pop();
+                if let Some(&(parent, _)) = work.last() {
+                    low_link[parent] = low_link[parent].min(low_link[vertex]);
+                }
+                if low_link[vertex] == index[vertex] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == vertex {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    /// Vertices that can reach each other ignoring edge directions.
+    /// Each component is sorted, and the components are ordered by their smallest vertex.
+    pub fn connected_components(&self) -> Vec<Vec<usize>> {
+        let mut parents: Vec<usize> = (0..self.len()).collect();
+        fn root(parents: &mut [usize], mut vertex: usize) -> usize {
+            while parents[vertex] != vertex {
+                parents[vertex] = parents[parents[vertex]];
+                vertex = parents[vertex];
+            }
+            vertex
+        }
+        for (from, to) in self.edges() {
+            let (from, to) = (root(&mut parents, from), root(&mut parents, to));
+            parents[from.max(to)] = from.min(to);
+        }
+
+        let mut components: Vec<Vec<usize>> = Vec::new();
+        let mut component_of_root = HashMap::new();
+        for vertex in 0..self.len() {
+            let root = root(&mut parents, vertex);
+            let idx = *component_of_root.entry(root).or_insert_with(|| {
+                components.push(Vec::new());
+                components.len() - 1
+            });
+            components[idx].push(vertex);
+        }
+        components
+    }
+
+    /// Graphviz DOT, labelling vertices with `name`.
+    pub fn to_dot<S: AsRef<str>>(&self, name: impl Fn(usize) -> S) -> String {
+        let mut dot = String::from("digraph {\n");
+        for vertex in 0..self.len() {
+            writeln!(dot, "    {vertex} [label=\"{}\"];", name(vertex).as_ref()).unwrap();
+        }
+        for (from, to) in self.edges() {
+            writeln!(dot, "    {from} -> {to};").unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// A weight for every ordered pair of vertices, `None` where there's no edge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdjacencyMatrix {
+    len: usize,
+    weights: Vec<Option<u32>>,
+}
+
+impl AdjacencyMatrix {
+    pub fn new(len: usize) -> AdjacencyMatrix {
+        AdjacencyMatrix {
+            len,
+            weights: vec![None; len * len],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, from: usize, to: usize) -> Option<u32> {
+        self.weights[from * self.len + to]
+    }
+
+    pub fn set(&mut self, from: usize, to: usize, weight: u32) {
+        self.weights[from * self.len + to] = Some(weight);
+    }
+
+    pub fn set_undirected(&mut self, one: usize, two: usize, weight: u32) {
+        self.set(one, two, weight);
+        self.set(two, one, weight);
+    }
+
+    /// Length of the shortest path between every pair of vertices, `None` if there's no path.
+    /// Lengths that don't fit in a u32 stop at u32::MAX.
+    /// https://en.wikipedia.org/wiki/Floyd–Warshall_algorithm
+    pub fn floyd_warshall(&self) -> AdjacencyMatrix {
+        let mut distances = self.clone();
+        for vertex in 0..self.len {
+            distances.set(vertex, vertex, 0);
+        }
+        for via in 0..self.len {
+            for from in 0..self.len {
+                let Some(first) = distances.get(from, via) else {
+                    continue;
+                };
+                for to in 0..self.len {
+                    let Some(second) = distances.get(via, to) else {
+                        continue;
+                    };
+                    let through = first.saturating_add(second);
+                    if distances
+                        .get(from, to)
+                        .is_none_or(|direct| through < direct)
+                    {
+                        distances.set(from, to, through);
+                    }
+                }
+            }
+        }
+        distances
+    }
+}
+
+impl From<&AdjacencyList> for AdjacencyMatrix {
+    /// Every edge gets a weight of 1.
+    fn from(list: &AdjacencyList) -> AdjacencyMatrix {
+        let mut matrix = AdjacencyMatrix::new(list.len());
+        for (from, to) in list.edges() {
+            matrix.set(from, to, 1);
+        }
+        matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_edges(len: usize, edges: &[(usize, usize)]) -> AdjacencyList {
+        let mut graph = AdjacencyList::new(len);
+        for (from, to) in edges {
+            graph.add_edge(*from, *to);
+        }
+        graph
+    }
+
+    fn sorted(mut components: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        for component in &mut components {
+            component.sort_unstable();
+        }
+        components.sort_unstable();
+        components
+    }
+
+    #[test]
+    fn interns_names() {
+        let mut interner = Interner::new();
+        assert!(interner.is_empty());
+        assert_eq!(interner.intern("b"), 0);
+        assert_eq!(interner.intern("a"), 1);
+        assert_eq!(interner.intern("b"), 0);
+        assert_eq!(interner.id("a"), Some(1));
+        assert_eq!(interner.id("c"), None);
+        assert_eq!(interner.name(1), "a");
+        assert_eq!(interner.names(), ["b", "a"]);
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn sorts_topologically() {
+        let graph = from_edges(5, &[(3, 1), (1, 0), (4, 0), (2, 4)]);
+        // 2 and 3 are both ready at the start, and the smaller goes first
+        assert_eq!(graph.topological_sort(), Ok(vec![2, 3, 1, 4, 0]));
+        assert_eq!(AdjacencyList::new(3).topological_sort(), Ok(vec![0, 1, 2]));
+        assert_eq!(AdjacencyList::default().topological_sort(), Ok(vec![]));
+    }
+
+    #[test]
+    fn reports_a_cycle() {
+        // 0 -> 1 -> 2 -> 3 -> 1, with 4 hanging off the cycle
+        let graph = from_edges(5, &[(0, 1), (1, 2), (2, 3), (3, 1), (3, 4)]);
+        let Err(Cycle(cycle)) = graph.topological_sort() else {
+            panic!("there's a cycle");
+        };
+        let mut sorted_cycle = cycle.clone();
+        sorted_cycle.sort_unstable();
+        assert_eq!(sorted_cycle, vec![1, 2, 3]);
+        for (idx, vertex) in cycle.iter().enumerate() {
+            let next = cycle[(idx + 1) % cycle.len()];
+            assert!(graph.neighbours(*vertex).contains(&next), "{cycle:?}");
+        }
+
+        let self_loop = from_edges(1, &[(0, 0)]);
+        assert_eq!(self_loop.topological_sort(), Err(Cycle(vec![0])));
+    }
+
+    #[test]
+    fn finds_strongly_connected_components() {
+        let graph = from_edges(
+            8,
+            &[
+                (0, 1),
+                (1, 2),
+                (2, 0),
+                (2, 3),
+                (3, 4),
+                (4, 3),
+                (5, 4),
+                (6, 6),
+            ],
+        );
+        let components = graph.strongly_connected_components();
+        assert_eq!(
+            sorted(components.clone()),
+            vec![vec![0, 1, 2], vec![3, 4], vec![5], vec![6], vec![7]]
+        );
+        // No edge leads to a component that comes later
+        let position = |vertex: usize| {
+            components
+                .iter()
+                .position(|component| component.contains(&vertex))
+                .unwrap()
+        };
+        for (from, to) in graph.edges() {
+            assert!(position(from) >= position(to), "{from} -> {to}");
+        }
+    }
+
+    #[test]
+    fn handles_deep_graphs() {
+        let len = 100_000;
+        let mut edges: Vec<_> = (1..len).map(|vertex| (vertex - 1, vertex)).collect();
+        edges.push((len - 1, 0));
+        let graph = from_edges(len, &edges);
+        assert_eq!(graph.strongly_connected_components().len(), 1);
+        assert_eq!(graph.connected_components().len(), 1);
+    }
+
+    #[test]
+    fn finds_connected_components() {
+        let graph = from_edges(7, &[(5, 1), (1, 3), (4, 6), (6, 4)]);
+        assert_eq!(
+            graph.connected_components(),
+            vec![vec![0], vec![1, 3, 5], vec![2], vec![4, 6]]
+        );
+    }
+
+    #[test]
+    fn finds_shortest_paths() {
+        let mut matrix = AdjacencyMatrix::new(4);
+        matrix.set(0, 1, 5);
+        matrix.set(1, 2, 3);
+        matrix.set(0, 2, 10);
+        matrix.set_undirected(2, 3, 1);
+        let distances = matrix.floyd_warshall();
+        assert_eq!(distances.get(0, 2), Some(8));
+        assert_eq!(distances.get(0, 3), Some(9));
+        assert_eq!(distances.get(3, 2), Some(1));
+        assert_eq!(distances.get(3, 0), None);
+        assert_eq!(distances.get(1, 1), Some(0));
+
+        let unweighted = AdjacencyMatrix::from(&from_edges(3, &[(0, 1), (1, 2)]));
+        assert_eq!(unweighted.floyd_warshall().get(0, 2), Some(2));
+    }
+
+    #[test]
+    fn long_paths_saturate() {
+        let mut matrix = AdjacencyMatrix::new(3);
+        matrix.set(0, 1, u32::MAX - 1);
+        matrix.set(1, 2, u32::MAX - 1);
+        let distances = matrix.floyd_warshall();
+        assert_eq!(distances.get(0, 1), Some(u32::MAX - 1));
+        assert_eq!(distances.get(0, 2), Some(u32::MAX));
+    }
+
+    #[test]
+    fn draws_dot() {
+        let graph = from_edges(2, &[(0, 1)]);
+        assert_eq!(
+            graph.to_dot(|vertex| ["a", "b"][vertex]),
+            "digraph {\n    0 [label=\"a\"];\n    1 [label=\"b\"];\n    0 -> 1;\n}\n"
+        );
+    }
+}
@@ -1,4 +1,8 @@
+pub mod graph;
 pub mod grid;
+pub mod linear;
+pub mod math;
+pub mod solution;
 
 #[macro_export]
 macro_rules! aoctest {
@@ -34,3 +38,45 @@ macro_rules! aoctest {
         }
     };
 }
+
+/// Registers every day of a year so the benchmarks and runners can find them.
+/// `common::solutions!(2024; 1 => day1, 2 => day2)` creates `pub const SOLUTIONS`.
+/// `16 => day16 + explain` also registers `day16::explain(input, part, format)`.
+#[macro_export]
+macro_rules! solutions {
+    ($year: expr; $($day_num: expr => $day: ident $(+ $explain: ident)?),* $(,)?) => {
+        pub const SOLUTIONS: &[$crate::solution::Solution] = &[
+            $($crate::solution::Solution {
+                year: $year,
+                day: $day_num,
+                parts: [
+                    $crate::solution::Part {
+                        run: |input| {
+                            std::hint::black_box($day::part1(input));
+                        },
+                        answer: |input| $crate::solution::Answer::to_answer(&$day::part1(input)),
+                        explain: $crate::explain!($day, 1 $(, $explain)?),
+                    },
+                    $crate::solution::Part {
+                        run: |input| {
+                            std::hint::black_box($day::part2(input));
+                        },
+                        answer: |input| $crate::solution::Answer::to_answer(&$day::part2(input)),
+                        explain: $crate::explain!($day, 2 $(, $explain)?),
+                    },
+                ],
+            }),*
+        ];
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! explain {
+    ($day: ident, $part: expr) => {
+        None
+    };
+    ($day: ident, $part: expr, $explain: ident) => {
+        Some(|input, format| $day::$explain(input, $part, format))
+    };
+}
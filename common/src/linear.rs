@@ -0,0 +1,515 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::math::{checked_crt, gcd, lcm, mod_inverse, mul_mod};
+
+/// A fraction in lowest terms, with a positive denominator.
+/// Arithmetic panics on overflow, which takes numbers far bigger than the puzzles use.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    pub const ZERO: Rational = Rational::integer(0);
+    pub const ONE: Rational = Rational::integer(1);
+
+    /// Panics if `denominator` is 0.
+    pub fn new(numerator: i128, denominator: i128) -> Rational {
+        assert!(denominator != 0, "{numerator}/0 isn't a number");
+        let divisor = gcd(numerator, denominator) * denominator.signum();
+        Rational {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        }
+    }
+
+    pub const fn integer(n: i128) -> Rational {
+        Rational {
+            numerator: n,
+            denominator: 1,
+        }
+    }
+
+    pub fn numerator(self) -> i128 {
+        self.numerator
+    }
+
+    pub fn denominator(self) -> i128 {
+        self.denominator
+    }
+
+    pub fn is_integer(self) -> bool {
+        self.denominator == 1
+    }
+
+    pub fn to_integer(self) -> Option<i128> {
+        self.is_integer().then_some(self.numerator)
+    }
+
+    /// Panics if this is 0.
+    pub fn recip(self) -> Rational {
+        Rational::new(self.denominator, self.numerator)
+    }
+
+    pub fn floor(self) -> i128 {
+        self.numerator.div_euclid(self.denominator)
+    }
+
+    pub fn ceil(self) -> i128 {
+        -(-self).floor()
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(n: i64) -> Rational {
+        Rational::integer(n as i128)
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        (self.numerator * other.denominator).cmp(&(other.numerator * self.denominator))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+
+    fn add(self, other: Rational) -> Rational {
+        let denominator = lcm(self.denominator, other.denominator);
+        Rational::new(
+            self.numerator * (denominator / self.denominator)
+                + other.numerator * (denominator / other.denominator),
+            denominator,
+        )
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+
+    fn sub(self, other: Rational) -> Rational {
+        self + -other
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+
+    fn mul(self, other: Rational) -> Rational {
+        // Cancelling across first keeps the products small
+        let one = gcd(self.numerator, other.denominator).max(1);
+        let two = gcd(other.numerator, self.denominator).max(1);
+        Rational::new(
+            (self.numerator / one) * (other.numerator / two),
+            (self.denominator / two) * (other.denominator / one),
+        )
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+
+    /// Panics when dividing by 0.
+    fn div(self, other: Rational) -> Rational {
+        self.mul(other.recip())
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational {
+            numerator: -self.numerator,
+            denominator: self.denominator,
+        }
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+/// Every `x` with `A x = b`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    /// The equations contradict each other.
+    Inconsistent,
+    Unique(Vec<Rational>),
+    /// `particular` plus any multiple of each direction. There's one direction per free variable.
+    Infinite {
+        particular: Vec<Rational>,
+        directions: Vec<Vec<Rational>>,
+    },
+}
+
+/// Solves `A x = b` exactly with Gauss-Jordan elimination. `a` has one row per equation and one
+/// column per variable; it doesn't have to be square. Panics if the rows have different lengths,
+/// or if there's a different number of rows in `a` and `b`.
+pub fn solve<R: AsRef<[i64]>>(a: &[R], b: &[i64]) -> Solution {
+    assert_eq!(a.len(), b.len(), "every equation needs a right hand side");
+    let variables = a.first().map_or(0, |row| row.as_ref().len());
+    let mut matrix: Vec<Vec<Rational>> = a
+        .iter()
+        .zip(b)
+        .map(|(row, result)| {
+            let row = row.as_ref();
+            assert_eq!(
+                row.len(),
+                variables,
+                "every equation needs the same variables"
+            );
+            row.iter()
+                .chain(std::iter::once(result))
+                .map(|n| Rational::from(*n))
+                .collect()
+        })
+        .collect();
+
+    // The column of the leading 1 in each of the first pivots.len() rows
+    let mut pivots = Vec::new();
+    for column in 0..variables {
+        let row = pivots.len();
+        let Some(found) = (row..matrix.len()).find(|r| matrix[*r][column] != Rational::ZERO) else {
+            continue;
+        };
+        matrix.swap(row, found);
+        let pivot = matrix[row][column];
+        for value in &mut matrix[row][column..] {
+            *value = *value / pivot;
+        }
+        let pivot_row = matrix[row].clone();
+        for (other, values) in matrix.iter_mut().enumerate() {
+            let factor = values[column];
+            if other == row || factor == Rational::ZERO {
+                continue;
+            }
+            for (value, pivot_value) in values[column..].iter_mut().zip(&pivot_row[column..]) {
+                *value = *value - factor * *pivot_value;
+            }
+        }
+        pivots.push(column);
+    }
+
+    // Whatever is left over reads 0 = result
+    if matrix[pivots.len()..]
+        .iter()
+        .any(|row| row[variables] != Rational::ZERO)
+    {
+        return Solution::Inconsistent;
+    }
+    let mut particular = vec![Rational::ZERO; variables];
+    for (row, column) in pivots.iter().enumerate() {
+        particular[*column] = matrix[row][variables];
+    }
+    if pivots.len() == variables {
+        return Solution::Unique(particular);
+    }
+    let directions = (0..variables)
+        .filter(|column| !pivots.contains(column))
+        .map(|free| {
+            let mut direction = vec![Rational::ZERO; variables];
+            direction[free] = Rational::ONE;
+            for (row, column) in pivots.iter().enumerate() {
+                direction[*column] = -matrix[row][free];
+            }
+            direction
+        })
+        .collect();
+    Solution::Infinite {
+        particular,
+        directions,
+    }
+}
+
+/// Finding the cheapest integer solution with several free variables is integer programming,
+/// which `min_cost_non_negative` doesn't attempt. Holds the number of free variables.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TooManyFreeVariables(pub usize);
+
+impl fmt::Display for TooManyFreeVariables {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} free variables, at most 1 is supported", self.0)
+    }
+}
+
+impl std::error::Error for TooManyFreeVariables {}
+
+/// The solution of `A x = b` in non-negative integers that minimises `costs · x`.
+/// Returns None if there's no such solution, if the cost has no minimum, or if the solution
+/// doesn't fit in an i64. With one free variable the solutions lie on a line, and the cheapest
+/// is at one end of the stretch where every variable is non-negative.
+pub fn min_cost_non_negative<R: AsRef<[i64]>>(
+    a: &[R],
+    b: &[i64],
+    costs: &[i64],
+) -> Result<Option<Vec<i64>>, TooManyFreeVariables> {
+    let (start, step) = match solve(a, b) {
+        Solution::Inconsistent => return Ok(None),
+        Solution::Unique(x) => match x.iter().map(|n| n.to_integer()).collect() {
+            Some(x) => (x, None),
+            None => return Ok(None),
+        },
+        Solution::Infinite {
+            particular,
+            directions,
+        } => {
+            if directions.len() > 1 {
+                return Err(TooManyFreeVariables(directions.len()));
+            }
+            match integer_line(&particular, &directions[0]) {
+                Some((start, step)) => (start, Some(step)),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let m = match &step {
+        None => 0,
+        Some(step) => {
+            // start + m * step >= 0 for every variable
+            let (mut lowest, mut highest) = (None::<i128>, None::<i128>);
+            for (x, dx) in start.iter().zip(step) {
+                match dx.cmp(&0) {
+                    Ordering::Greater => {
+                        let bound = Rational::new(-x, *dx).ceil();
+                        lowest = Some(lowest.map_or(bound, |lowest| lowest.max(bound)));
+                    }
+                    Ordering::Less => {
+                        let bound = Rational::new(*x, -dx).floor();
+                        highest = Some(highest.map_or(bound, |highest| highest.min(bound)));
+                    }
+                    Ordering::Equal if *x < 0 => return Ok(None),
+                    Ordering::Equal => {}
+                }
+            }
+            let slope: i128 = costs.iter().zip(step).map(|(c, dx)| *c as i128 * dx).sum();
+            let m = match (slope.cmp(&0), lowest, highest) {
+                (Ordering::Greater | Ordering::Equal, Some(lowest), _) => lowest,
+                (Ordering::Less | Ordering::Equal, _, Some(highest)) => highest,
+                _ => return Ok(None),
+            };
+            if lowest.is_some_and(|lowest| m < lowest) || highest.is_some_and(|highest| m > highest)
+            {
+                return Ok(None);
+            }
+            m
+        }
+    };
+
+    let step = step.unwrap_or_else(|| vec![0; start.len()]);
+    Ok(start
+        .iter()
+        .zip(&step)
+        .map(|(x, dx)| i64::try_from(x + m * dx).ok().filter(|x| *x >= 0))
+        .collect())
+}
+
+// The integer points on the line `particular + t * direction`, as `start + m * step` for integer m.
+// With particular written as n / l, t has to be s / l for an integer s with s * d ≡ -n (mod l) for
+// every variable, once direction is scaled to the smallest integer vector d along it.
+fn integer_line(particular: &[Rational], direction: &[Rational]) -> Option<(Vec<i128>, Vec<i128>)> {
+    let scale = direction
+        .iter()
+        .fold(1, |scale, d| lcm(scale, d.denominator()));
+    let direction: Vec<i128> = direction
+        .iter()
+        .map(|d| d.numerator() * (scale / d.denominator()))
+        .collect();
+    let divisor = direction.iter().fold(0, |divisor, d| gcd(divisor, *d));
+    let direction: Vec<i128> = direction.iter().map(|d| d / divisor).collect();
+
+    let l = particular.iter().fold(1, |l, p| lcm(l, p.denominator()));
+    let numerators: Vec<i128> = particular
+        .iter()
+        .map(|p| p.numerator() * (l / p.denominator()))
+        .collect();
+    let mut congruences = Vec::with_capacity(direction.len());
+    for (n, d) in numerators.iter().zip(&direction) {
+        let g = gcd(*d, l);
+        if n % g != 0 {
+            return None;
+        }
+        let modulus = l / g;
+        let inverse = mod_inverse(d / g, modulus)?;
+        congruences.push((mul_mod(-n / g, inverse, modulus), modulus));
+    }
+    let s = checked_crt(&congruences).ok()??;
+
+    let t = Rational::new(s.residue, l);
+    let stride = Rational::new(s.modulus, l);
+    let start = particular
+        .iter()
+        .zip(&direction)
+        .map(|(p, d)| (*p + t * Rational::integer(*d)).to_integer())
+        .collect::<Option<_>>()?;
+    let step = direction
+        .iter()
+        .map(|d| (stride * Rational::integer(*d)).to_integer())
+        .collect::<Option<_>>()?;
+    Some((start, step))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(numerator: i128, denominator: i128) -> Rational {
+        Rational::new(numerator, denominator)
+    }
+
+    // A x, for checking solutions.
+    fn apply(a: &[&[i64]], x: &[Rational]) -> Vec<Rational> {
+        a.iter()
+            .map(|row| {
+                row.iter()
+                    .zip(x)
+                    .fold(Rational::ZERO, |sum, (a, x)| sum + Rational::from(*a) * *x)
+            })
+            .collect()
+    }
+
+    fn rationals(b: &[i64]) -> Vec<Rational> {
+        b.iter().map(|n| Rational::from(*n)).collect()
+    }
+
+    #[test]
+    fn rationals_are_in_lowest_terms() {
+        assert_eq!(r(6, -4), r(-3, 2));
+        assert_eq!(r(-3, 2).numerator(), -3);
+        assert_eq!(r(-3, 2).denominator(), 2);
+        assert_eq!(r(0, -5), Rational::ZERO);
+        assert_eq!(r(1, 2) + r(1, 3), r(5, 6));
+        assert_eq!(r(1, 2) - r(1, 3), r(1, 6));
+        assert_eq!(r(2, 3) * r(3, 4), r(1, 2));
+        assert_eq!(r(2, 3) / r(4, 3), r(1, 2));
+        assert_eq!(r(-2, 3).recip(), r(-3, 2));
+        assert_eq!((r(-7, 2).floor(), r(-7, 2).ceil()), (-4, -3));
+        assert_eq!((r(7, 2).floor(), r(7, 2).ceil()), (3, 4));
+        assert_eq!(r(8, 4).to_integer(), Some(2));
+        assert_eq!(r(7, 4).to_integer(), None);
+        assert!(r(-1, 2) < r(-1, 3));
+        assert_eq!(r(-3, 6).to_string(), "-1/2");
+        assert_eq!(r(4, 2).to_string(), "2");
+    }
+
+    #[test]
+    fn solves_unique_systems() {
+        let a: [&[i64]; 2] = [&[94, 22], &[34, 67]];
+        assert_eq!(
+            solve(&a, &[8400, 5400]),
+            Solution::Unique(vec![Rational::from(80), Rational::from(40)])
+        );
+        // The solution doesn't have to be whole
+        let a: [&[i64]; 2] = [&[1, 1], &[1, -1]];
+        assert_eq!(solve(&a, &[1, 0]), Solution::Unique(vec![r(1, 2), r(1, 2)]));
+        assert_eq!(min_cost_non_negative(&a, &[1, 0], &[1, 1]), Ok(None));
+        // More equations than variables is fine while they agree
+        let a: [&[i64]; 3] = [&[1, 1], &[1, -1], &[2, 0]];
+        assert_eq!(
+            solve(&a, &[4, 2, 6]),
+            Solution::Unique(vec![Rational::from(3), Rational::from(1)])
+        );
+    }
+
+    #[test]
+    fn spots_inconsistent_systems() {
+        let a: [&[i64]; 2] = [&[1, 1], &[2, 2]];
+        assert_eq!(solve(&a, &[1, 3]), Solution::Inconsistent);
+        assert_eq!(min_cost_non_negative(&a, &[1, 3], &[1, 1]), Ok(None));
+        let a: [&[i64]; 3] = [&[1, 1], &[1, -1], &[2, 0]];
+        assert_eq!(solve(&a, &[4, 2, 7]), Solution::Inconsistent);
+        let a: [&[i64]; 1] = [&[0, 0]];
+        assert_eq!(solve(&a, &[1]), Solution::Inconsistent);
+    }
+
+    #[test]
+    fn describes_every_solution_with_free_variables() {
+        let a: [&[i64]; 2] = [&[1, 2, 3], &[2, 4, 7]];
+        let b = [6, 13];
+        let Solution::Infinite {
+            particular,
+            directions,
+        } = solve(&a, &b)
+        else {
+            panic!("one free variable");
+        };
+        assert_eq!(directions.len(), 1);
+        assert_eq!(apply(&a, &particular), rationals(&b));
+        assert_eq!(apply(&a, &directions[0]), rationals(&[0, 0]));
+
+        let a: [&[i64]; 1] = [&[1, 1, 1]];
+        let Solution::Infinite {
+            particular,
+            directions,
+        } = solve(&a, &[1])
+        else {
+            panic!("two free variables");
+        };
+        assert_eq!(directions.len(), 2);
+        for direction in &directions {
+            assert_eq!(apply(&a, direction), rationals(&[0]));
+            let moved: Vec<_> = particular
+                .iter()
+                .zip(direction)
+                .map(|(p, d)| *p + r(5, 3) * *d)
+                .collect();
+            assert_eq!(apply(&a, &moved), rationals(&[1]));
+        }
+        assert_eq!(
+            min_cost_non_negative(&a, &[1], &[1, 1, 1]),
+            Err(TooManyFreeVariables(2))
+        );
+        let none: [&[i64]; 2] = [&[0, 0], &[0, 0]];
+        assert_eq!(
+            min_cost_non_negative(&none, &[0, 0], &[3, 1]),
+            Err(TooManyFreeVariables(2))
+        );
+    }
+
+    #[test]
+    fn finds_the_cheapest_whole_solution() {
+        // Parallel buttons, A costing 3 and B costing 1
+        let a: [&[i64]; 2] = [&[4, 1], &[4, 1]];
+        assert_eq!(
+            min_cost_non_negative(&a, &[10, 10], &[3, 1]),
+            Ok(Some(vec![2, 2]))
+        );
+        let a: [&[i64]; 2] = [&[2, 1], &[2, 1]];
+        assert_eq!(
+            min_cost_non_negative(&a, &[10, 10], &[3, 1]),
+            Ok(Some(vec![0, 10]))
+        );
+        // Only every fifth a is whole: a = 1 + 5k, b = 2 - 3k
+        let a: [&[i64]; 1] = [&[6, 10]];
+        assert_eq!(
+            min_cost_non_negative(&a, &[26], &[1, 1]),
+            Ok(Some(vec![1, 2]))
+        );
+        assert_eq!(min_cost_non_negative(&a, &[14], &[1, 1]), Ok(None));
+        assert_eq!(min_cost_non_negative(&a, &[15], &[1, 1]), Ok(None));
+        // Nothing bounds a from above, so there's no cheapest when it pays to press it
+        let a: [&[i64]; 1] = [&[1, -1]];
+        assert_eq!(min_cost_non_negative(&a, &[3], &[-2, 1]), Ok(None));
+        assert_eq!(
+            min_cost_non_negative(&a, &[3], &[1, 1]),
+            Ok(Some(vec![3, 0]))
+        );
+    }
+}
@@ -0,0 +1,488 @@
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+/// The primitive integers, signed and unsigned.
+pub trait Integer:
+    Copy
+    + Ord
+    + Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const TEN: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn rem_euclid(self, modulus: Self) -> Self;
+    fn isqrt(self) -> Self;
+}
+
+/// The signed primitive integers.
+pub trait Signed: Integer + Neg<Output = Self> {}
+
+macro_rules! integer {
+    ($($t: ty),*) => {
+        $(impl Integer for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const TEN: Self = 10;
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+
+            fn rem_euclid(self, modulus: Self) -> Self {
+                <$t>::rem_euclid(self, modulus)
+            }
+
+            fn isqrt(self) -> Self {
+                <$t>::isqrt(self)
+            }
+        })*
+    };
+}
+
+integer!(i32, i64, i128, u32, u64, u128, usize);
+
+impl Signed for i32 {}
+impl Signed for i64 {}
+impl Signed for i128 {}
+
+/// A solution to a system of congruences: every `residue + k * modulus`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Congruence<T> {
+    pub residue: T,
+    pub modulus: T,
+}
+
+/// An intermediate result didn't fit in the integer type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Overflow;
+
+/// Always non-negative. `gcd(0, 0)` is 0.
+pub fn gcd<T: Integer>(a: T, b: T) -> T {
+    let (mut a, mut b) = (abs(a), abs(b));
+    while b != T::ZERO {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Panics if the result doesn't fit in `T`.
+pub fn lcm<T: Integer>(a: T, b: T) -> T {
+    checked_lcm(a, b).expect("lcm overflowed")
+}
+
+pub fn checked_lcm<T: Integer>(a: T, b: T) -> Option<T> {
+    if a == T::ZERO || b == T::ZERO {
+        return Some(T::ZERO);
+    }
+    (abs(a) / gcd(a, b)).checked_mul(abs(b))
+}
+
+/// Returns `(g, x, y)` with `a * x + b * y == g == gcd(a, b)`.
+pub fn extended_gcd<T: Signed>(a: T, b: T) -> (T, T, T) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (T::ONE, T::ZERO);
+    let (mut old_y, mut y) = (T::ZERO, T::ONE);
+    while r != T::ZERO {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_x, x) = (x, old_x - quotient * x);
+        (old_y, y) = (y, old_y - quotient * y);
+    }
+    if old_r < T::ZERO {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// `x` in `0..modulus` with `a * x ≡ 1 (mod modulus)`, if `a` and `modulus` are coprime.
+/// The coefficients are kept reduced modulo `modulus`, so this works for unsigned types too.
+pub fn mod_inverse<T: Integer>(a: T, modulus: T) -> Option<T> {
+    if modulus <= T::ZERO {
+        return None;
+    }
+    if modulus == T::ONE {
+        return Some(T::ZERO);
+    }
+    let (mut old_r, mut r) = (modulus, a.rem_euclid(modulus));
+    let (mut old_x, mut x) = (T::ZERO, T::ONE);
+    while r != T::ZERO {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_x, x) = (x, sub_mod(old_x, mul_mod(quotient, x, modulus), modulus));
+    }
+    (old_r == T::ONE).then(|| old_x.rem_euclid(modulus))
+}
+
+/// `a * b mod modulus`, without overflowing even when `a * b` doesn't fit in `T`.
+pub fn mul_mod<T: Integer>(a: T, b: T, modulus: T) -> T {
+    let (mut a, mut b) = (a.rem_euclid(modulus), b.rem_euclid(modulus));
+    if let Some(product) = a.checked_mul(b) {
+        return product % modulus;
+    }
+    // Double and add
+    let two = T::ONE + T::ONE;
+    let mut result = T::ZERO;
+    while b > T::ZERO {
+        if b % two == T::ONE {
+            result = add_mod(result, a, modulus);
+        }
+        a = add_mod(a, a, modulus);
+        b = b / two;
+    }
+    result
+}
+
+/// `base ^ exponent mod modulus`, by repeated squaring.
+/// A negative exponent raises the inverse of `base`, and panics if `base` has no inverse.
+pub fn mod_pow<T: Integer>(base: T, exponent: T, modulus: T) -> T {
+    let two = T::ONE + T::ONE;
+    let mut result = T::ONE % modulus;
+    let mut base = if exponent < T::ZERO {
+        mod_inverse(base, modulus).expect("a negative exponent needs a base with an inverse")
+    } else {
+        base.rem_euclid(modulus)
+    };
+    // Dividing rounds towards 0, so a negative exponent counts up to 0 without being negated,
+    // which the minimum of a signed type can't be
+    let mut exponent = exponent;
+    while exponent != T::ZERO {
+        if exponent % two != T::ZERO {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent = exponent / two;
+    }
+    result
+}
+
+/// Solves `x ≡ residue (mod modulus)` for every pair at once. The moduli have to be positive, but
+/// don't need to be coprime. Returns None if the congruences contradict each other.
+/// Panics if the combined modulus doesn't fit in `T`.
+pub fn crt<T: Integer>(congruences: &[(T, T)]) -> Option<Congruence<T>> {
+    checked_crt(congruences).expect("crt overflowed")
+}
+
+/// Like `crt`, with an error instead of a panic when the combined modulus doesn't fit in `T`.
+pub fn checked_crt<T: Integer>(congruences: &[(T, T)]) -> Result<Option<Congruence<T>>, Overflow> {
+    let mut solution = Congruence {
+        residue: T::ZERO,
+        modulus: T::ONE,
+    };
+    for &(residue, modulus) in congruences {
+        let residue = residue.rem_euclid(modulus);
+        // x = solution.residue + solution.modulus * k, and that has to be residue mod modulus.
+        // So solution.modulus * k ≡ residue - solution.residue (mod modulus)
+        let g = gcd(solution.modulus, modulus);
+        let difference = sub_mod(residue, solution.residue.rem_euclid(modulus), modulus);
+        if difference % g != T::ZERO {
+            return Ok(None);
+        }
+        let reduced = modulus / g;
+        let inverse = mod_inverse(solution.modulus / g, reduced)
+            .expect("dividing by the gcd leaves coprime numbers");
+        let k = mul_mod(difference / g, inverse, reduced);
+        let combined = (solution.modulus / g)
+            .checked_mul(modulus)
+            .ok_or(Overflow)?;
+        let residue = solution
+            .modulus
+            .checked_mul(k)
+            .and_then(|offset| offset.checked_add(solution.residue))
+            .ok_or(Overflow)?;
+        solution = Congruence {
+            residue: residue % combined,
+            modulus: combined,
+        };
+    }
+    Ok(Some(solution))
+}
+
+/// Floor of the square root. Panics on negative numbers.
+pub fn isqrt<T: Integer>(n: T) -> T {
+    n.isqrt()
+}
+
+/// Number of decimal digits, ignoring the sign. 0 has one digit.
+pub fn num_digits<T: Integer>(n: T) -> u32 {
+    // Dividing rounds towards 0, so this works for negative numbers without taking the absolute
+    // value, which the minimum of a signed type doesn't have
+    let mut n = n / T::TEN;
+    let mut digits = 1;
+    while n != T::ZERO {
+        n = n / T::TEN;
+        digits += 1;
+    }
+    digits
+}
+
+/// The decimal digits of `n`, most significant first, ignoring the sign.
+pub fn digits<T: Integer>(n: T) -> Vec<T> {
+    let mut digits = Vec::with_capacity(num_digits(n) as usize);
+    let mut n = n;
+    loop {
+        digits.push(abs(n % T::TEN));
+        n = n / T::TEN;
+        if n == T::ZERO {
+            break;
+        }
+    }
+    digits.reverse();
+    digits
+}
+
+/// `10 ^ num_digits(n)`, the smallest power of 10 with more digits than `n`.
+/// Panics if that doesn't fit in `T`.
+pub fn next_power_of_10<T: Integer>(n: T) -> T {
+    checked_next_power_of_10(n).expect("next_power_of_10 overflowed")
+}
+
+pub fn checked_next_power_of_10<T: Integer>(n: T) -> Option<T> {
+    checked_next_power(n, T::TEN)
+}
+
+/// The smallest power of `base` with more digits in that base than `n`, ignoring the sign.
+/// None if it doesn't fit in `T`. Panics if `base` is less than 2.
+pub fn checked_next_power<T: Integer>(n: T, base: T) -> Option<T> {
+    assert!(base > T::ONE, "the base has to be at least 2");
+    // Divides like num_digits, so the minimum of a signed type works too
+    let mut n = n / base;
+    let mut power = base;
+    while n != T::ZERO {
+        n = n / base;
+        power = power.checked_mul(base)?;
+    }
+    Some(power)
+}
+
+/// The digits of `b` written after the digits of `a`, so `concat(12, 345) == 12345`.
+/// Panics if the result doesn't fit in `T`.
+pub fn concat<T: Integer>(a: T, b: T) -> T {
+    checked_concat(a, b).expect("concat overflowed")
+}
+
+pub fn checked_concat<T: Integer>(a: T, b: T) -> Option<T> {
+    a.checked_mul(checked_next_power_of_10(b)?)?.checked_add(b)
+}
+
+fn abs<T: Integer>(n: T) -> T {
+    if n < T::ZERO {
+        T::ZERO - n
+    } else {
+        n
+    }
+}
+
+// Both arguments have to be in 0..modulus.
+fn add_mod<T: Integer>(a: T, b: T, modulus: T) -> T {
+    if a >= modulus - b {
+        a - (modulus - b)
+    } else {
+        a + b
+    }
+}
+
+// Both arguments have to be in 0..modulus.
+fn sub_mod<T: Integer>(a: T, b: T, modulus: T) -> T {
+    if a >= b {
+        a - b
+    } else {
+        a + (modulus - b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_and_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(-12, 18), 6);
+        assert_eq!(gcd(12u64, 0), 12);
+        assert_eq!(gcd(0, 0), 0);
+        assert_eq!(gcd(17i128, 5), 1);
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(-4, 6), 12);
+        assert_eq!(lcm(0, 6), 0);
+        assert_eq!(checked_lcm(u64::MAX, u64::MAX - 1), None);
+    }
+
+    #[test]
+    fn extended_gcd_gives_coefficients() {
+        for (a, b) in [(240i64, 46), (-240, 46), (240, -46), (0, 5), (5, 0), (7, 7)] {
+            let (g, x, y) = extended_gcd(a, b);
+            assert_eq!(g, gcd(a, b), "{a} {b}");
+            assert_eq!(a * x + b * y, g, "{a} {b}");
+        }
+    }
+
+    #[test]
+    fn mod_inverse_only_for_coprime_numbers() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(3u64, 11), Some(4));
+        assert_eq!(mod_inverse(4, 6), None);
+        assert_eq!(mod_inverse(0, 6), None);
+        assert_eq!(mod_inverse(5, 1), Some(0));
+        assert_eq!(mod_inverse(5, 0), None);
+        assert_eq!(mod_inverse(5, -7), None);
+        let modulus = u64::MAX - 58; // The largest prime below 2^64
+        let inverse = mod_inverse(u64::MAX - 100, modulus).unwrap();
+        assert_eq!(mul_mod(inverse, u64::MAX - 100, modulus), 1);
+    }
+
+    #[test]
+    fn mul_mod_and_mod_pow_dont_overflow() {
+        let expected = |a: u64, b: u64, m: u64| ((a as u128 * b as u128) % m as u128) as u64;
+        for (a, b, m) in [
+            (u64::MAX, u64::MAX, u64::MAX),
+            (u64::MAX - 1, u64::MAX - 2, u64::MAX),
+            (u64::MAX - 1, u64::MAX - 2, u64::MAX - 58),
+            (1 << 63, 3, (1 << 63) + 1),
+            (12345, 67890, 1000),
+        ] {
+            assert_eq!(mul_mod(a, b, m), expected(a, b, m), "{a} * {b} mod {m}");
+        }
+        assert_eq!(mul_mod(-7i64, 3, 5), 4);
+        assert_eq!(mod_pow(2u64, 10, 1000), 24);
+        assert_eq!(mod_pow(5, 0, 7), 1);
+        assert_eq!(mod_pow(5, 3, 1), 0);
+        // Fermat's little theorem, with a prime too large to square in a u64
+        let prime = u64::MAX - 58;
+        assert_eq!(mod_pow(u64::MAX / 3, prime - 1, prime), 1);
+    }
+
+    #[test]
+    fn mod_pow_with_negative_exponents() {
+        // 3 * 5 ≡ 1 (mod 7), so 3^-1 is 5
+        assert_eq!(mod_pow(3i64, -1, 7), 5);
+        assert_eq!(mod_pow(3i64, -2, 7), 4);
+        for exponent in -20i64..=20 {
+            let power = mod_pow(3, exponent, 7);
+            assert_eq!(
+                mul_mod(power, mod_pow(3, -exponent, 7), 7),
+                1,
+                "3^{exponent}"
+            );
+        }
+        assert_eq!(
+            mod_pow(-2i64, -3, 11),
+            mod_pow(mod_inverse(-8, 11).unwrap(), 1, 11)
+        );
+        assert_eq!(mod_pow(5i64, -3, 1), 0);
+        // i32::MIN can't be negated. It's a multiple of 4, so by Fermat's little theorem it's 1
+        assert_eq!(mod_pow(2i32, i32::MIN, 5), 1);
+        assert_eq!(mod_pow(2i32, i32::MIN + 1, 5), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "a negative exponent needs a base with an inverse")]
+    fn mod_pow_without_an_inverse() {
+        mod_pow(4i64, -1, 6);
+    }
+
+    #[test]
+    fn crt_with_coprime_moduli() {
+        assert_eq!(
+            crt(&[(2, 3), (3, 5), (2, 7)]),
+            Some(Congruence {
+                residue: 23,
+                modulus: 105
+            })
+        );
+        assert_eq!(
+            crt(&[(-1, 101), (-1, 103)]),
+            Some(Congruence {
+                residue: 101 * 103 - 1,
+                modulus: 101 * 103
+            })
+        );
+        assert_eq!(
+            crt::<i32>(&[]),
+            Some(Congruence {
+                residue: 0,
+                modulus: 1
+            })
+        );
+    }
+
+    #[test]
+    fn crt_with_moduli_that_share_factors() {
+        assert_eq!(
+            crt(&[(2u64, 4), (4, 6)]),
+            Some(Congruence {
+                residue: 10,
+                modulus: 12
+            })
+        );
+        assert_eq!(
+            crt(&[(3, 6), (3, 9), (0, 1)]),
+            Some(Congruence {
+                residue: 3,
+                modulus: 18
+            })
+        );
+        // Even and odd at once
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[(1, 2), (0, 4)]), None);
+        assert_eq!(
+            checked_crt(&[(0u64, u64::MAX), (1, u64::MAX - 1)]),
+            Err(Overflow)
+        );
+    }
+
+    #[test]
+    fn square_roots_round_down() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16u64), 4);
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+    }
+
+    #[test]
+    fn decimal_digits() {
+        assert_eq!(num_digits(0), 1);
+        assert_eq!(num_digits(9), 1);
+        assert_eq!(num_digits(10), 2);
+        assert_eq!(num_digits(-10), 2);
+        assert_eq!(num_digits(u64::MAX), 20);
+        assert_eq!(num_digits(i32::MIN), 10);
+        assert_eq!(num_digits(i64::MIN), 19);
+        assert_eq!(num_digits(i128::MIN), 39);
+        assert_eq!(digits(0), vec![0]);
+        assert_eq!(digits(1203u32), vec![1, 2, 0, 3]);
+        assert_eq!(digits(-45), vec![4, 5]);
+        assert_eq!(digits(i32::MIN), vec![2, 1, 4, 7, 4, 8, 3, 6, 4, 8]);
+        assert_eq!(next_power_of_10(0), 10);
+        assert_eq!(next_power_of_10(99), 100);
+        assert_eq!(next_power_of_10(100u64), 1000);
+        assert_eq!(checked_next_power_of_10(u64::MAX), None);
+        assert_eq!(checked_next_power_of_10(-99i32), Some(100));
+        assert_eq!(checked_next_power_of_10(i32::MIN), None);
+        assert_eq!(checked_next_power(0u64, 2), Some(2));
+        assert_eq!(checked_next_power(5u64, 2), Some(8));
+        assert_eq!(checked_next_power(8u64, 2), Some(16));
+        assert_eq!(checked_next_power(26u64, 3), Some(27));
+        assert_eq!(checked_next_power(27u64, 3), Some(81));
+        assert_eq!(checked_next_power(-16i32, 16), Some(256));
+        assert_eq!(checked_next_power(u64::MAX >> 1, 2), Some(1 << 63));
+        assert_eq!(checked_next_power(u64::MAX, 2), None);
+        assert_eq!(checked_next_power(i64::MIN, 2), None);
+        assert_eq!(concat(12, 345), 12345);
+        assert_eq!(concat(6, 0), 60);
+        assert_eq!(checked_concat(u64::MAX, 1), None);
+    }
+}
@@ -0,0 +1,59 @@
+/// One day's solution, as registered by a year crate with `common::solutions!`.
+pub struct Solution {
+    pub year: u32,
+    pub day: u32,
+    pub parts: [Part; 2],
+}
+
+pub struct Part {
+    /// Runs the part and throws away the answer. This is what gets benchmarked.
+    pub run: fn(&str),
+    /// Runs the part and formats the answer the way it would be submitted.
+    pub answer: fn(&str) -> String,
+    /// Describes how the answer was reached, for the days registered with `+ explain`.
+    pub explain: Option<fn(&str, Format) -> String>,
+}
+
+/// How `Part::explain` should write its description.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Solution {
+    pub fn part(&self, part: u32) -> Option<&Part> {
+        match part {
+            1 => Some(&self.parts[0]),
+            2 => Some(&self.parts[1]),
+            _ => None,
+        }
+    }
+
+    /// Path of the puzzle input, relative to the workspace root.
+    pub fn input_path(&self) -> String {
+        format!("y{}/src/day{}/input.txt", self.year, self.day)
+    }
+}
+
+pub trait Answer {
+    fn to_answer(&self) -> String;
+}
+
+macro_rules! display_answer {
+    ($($t: ty),*) => {
+        $(impl Answer for $t {
+            fn to_answer(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+display_answer!(i32, i64, u32, u64, usize, String);
+
+impl Answer for (usize, usize) {
+    fn to_answer(&self) -> String {
+        format!("{},{}", self.0, self.1)
+    }
+}
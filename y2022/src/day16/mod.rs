@@ -1,137 +1,292 @@
 use ahash::AHashMap;
+use common::graph::{AdjacencyMatrix, Interner};
+use common::solution::Format;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
 // Shoutout to /u/zopatista and others from https://redd.it/zo21au
+// Only valves that release steam are worth travelling to, so there are few enough of them to keep
+// the set of opened valves in a bitmask. A search over routes records the most steam each set can
+// release, and the elephant and I open disjoint sets.
 #[inline]
 pub fn part1(input: &str) -> u32 {
-    let valves = parse(input);
-    let distances = min_distances_floyd_warshall(&valves);
-    let important_valves: Vec<_> = valves
-        .iter()
-        .filter(|v| v.steam > 0)
-        .map(|v| v.idx)
-        .collect();
-    let starting_valve = valves.iter().find(|valve| valve.name == "AA").unwrap().idx;
-    let volcano = Volcano {
-        valves,
-        important_valves,
-        distances,
-    };
-
-    find_max_flow(&volcano, starting_valve, Vec::new(), 0, 0, 30)
+    let volcano = Volcano::new(parse(input));
+    volcano.best_per_subset(30).into_iter().max().unwrap_or(0)
 }
 
 #[inline]
-pub fn part2(_input: &str) -> u32 {
-    0
+pub fn part2(input: &str) -> u32 {
+    let volcano = Volcano::new(parse(input));
+    let (mine, elephants) = volcano.best_split(26);
+    mine.steam + elephants.steam
 }
 
-// https://en.wikipedia.org/wiki/Floyd–Warshall_algorithm
-#[allow(clippy::needless_range_loop)]
-fn min_distances_floyd_warshall(valves: &[Valve]) -> Vec<Vec<u32>> {
-    let n = valves.len();
-    let mut distances = vec![vec![0; valves.len()]; valves.len()];
-
-    for i in 0..n {
-        for j in 0..n {
-            if i == j {
-                distances[i][j] = 0;
-            } else {
-                distances[i][j] = 1000;
-            }
-        }
+/// Who opens which valves and when, for the best answer to either part.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValvePlan {
+    pub minutes: u32,
+    pub steam: u32,
+    pub routes: Vec<Route>,
+}
+
+/// The valves one of us opens, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Route {
+    pub who: String,
+    pub steam: u32,
+    pub steps: Vec<Step>,
+}
+
+/// Walking from one valve to the next and opening it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Step {
+    pub from: String,
+    pub to: String,
+    pub travel: u32,
+    /// The minute the valve is opened in. It starts releasing steam the minute after.
+    pub minute: u32,
+    pub rate: u32,
+    /// Steam this valve releases before time runs out.
+    pub released: u32,
+    /// Steam released by this valve and the ones opened before it on this route.
+    pub total: u32,
+}
+
+/// The plan for part 1, or for part 2 with the elephant's help.
+pub fn plan(input: &str, elephant: bool) -> ValvePlan {
+    let volcano = Volcano::new(parse(input));
+    let (minutes, plans) = if elephant {
+        let (mine, elephants) = volcano.best_split(26);
+        (26, vec![("Me", mine), ("Elephant", elephants)])
+    } else {
+        let best = volcano.best_per_subset(30);
+        let (subset, steam) = best
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, steam)| **steam)
+            .map_or((0, 0), |(subset, steam)| (subset, *steam));
+        (30, vec![("Me", Plan { subset, steam })])
+    };
+
+    let routes: Vec<Route> = plans
+        .into_iter()
+        .map(|(who, plan)| Route {
+            who: who.to_string(),
+            steam: plan.steam,
+            steps: volcano.steps(minutes, plan),
+        })
+        .collect();
+    ValvePlan {
+        minutes,
+        steam: routes.iter().map(|route| route.steam).sum(),
+        routes,
     }
-    for valve in valves.iter() {
-        for edge in valve.edges.iter() {
-            distances[valve.idx][*edge] = 1;
-            distances[*edge][valve.idx] = 1;
+}
+
+pub fn explain(input: &str, part: u32, format: Format) -> String {
+    let plan = plan(input, part == 2);
+    match format {
+        Format::Text => plan.to_string(),
+        Format::Json => {
+            serde_json::to_string_pretty(&plan).expect("plans can always be serialized")
         }
     }
-    for k in 0..n {
-        for i in 0..n {
-            for j in 0..n {
-                if distances[i][j] > distances[i][k] + distances[j][k] {
-                    distances[i][j] = distances[i][k] + distances[j][k];
-                    distances[j][i] = distances[i][k] + distances[j][k];
-                }
+}
+
+impl fmt::Display for ValvePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} steam released in {} minutes",
+            self.steam, self.minutes
+        )?;
+        for route in &self.routes {
+            writeln!(f, "{}, releasing {}", route.who, route.steam)?;
+            for step in &route.steps {
+                writeln!(
+                    f,
+                    "    minute {:>2}: {} -> {} ({} min), open {} (rate {:>2}) releasing {:>4}, total {:>4}",
+                    step.minute,
+                    step.from,
+                    step.to,
+                    step.travel,
+                    step.to,
+                    step.rate,
+                    step.released,
+                    step.total
+                )?;
             }
         }
+        Ok(())
     }
+}
 
-    distances
+fn min_distances(valves: &[Valve]) -> AdjacencyMatrix {
+    let mut tunnels = AdjacencyMatrix::new(valves.len());
+    for valve in valves {
+        for edge in &valve.edges {
+            tunnels.set_undirected(valve.idx, *edge, 1);
+        }
+    }
+    tunnels.floyd_warshall()
 }
 
-#[allow(clippy::too_many_arguments)]
-fn find_max_flow(
-    volcano: &Volcano,
-    current_valve: usize,
-    mut opened_so_far: Vec<usize>,
-    steam_so_far: u32,
-    steam_rate: u32,
-    remaining_time: u32,
-) -> u32 {
-    let valve = &volcano.valves[current_valve];
-    let mut results = Vec::with_capacity(valve.edge_names.len() + 1);
+/// A set of opened valves, as a bitmask over `Volcano::important_valves`, and the most steam
+/// opening exactly those valves can release.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+struct Plan {
+    subset: usize,
+    steam: u32,
+}
 
-    // Open the valve
-    opened_so_far.push(current_valve);
-    let increased_steam_rate = steam_rate + valve.steam; // Opened the valve
-
-    for destination in &volcano.important_valves {
-        let time_to_destination = volcano.distances[current_valve][*destination];
-        let time_spent_opening = if valve.steam > 0 { 1 } else { 0 }; // Spent 1 minute opening the valve
-        if opened_so_far.contains(destination)
-            || time_to_destination + time_spent_opening > remaining_time
-        {
-            continue;
+struct Volcano {
+    valves: Vec<Valve>,
+    important_valves: Vec<usize>,
+    distances: AdjacencyMatrix,
+    start: usize,
+}
+
+impl Volcano {
+    fn new(valves: Vec<Valve>) -> Volcano {
+        let distances = min_distances(&valves);
+        let important_valves = valves
+            .iter()
+            .filter(|v| v.steam > 0)
+            .map(|v| v.idx)
+            .collect();
+        let start = valves.iter().find(|valve| valve.name == "AA").unwrap().idx;
+        Volcano {
+            valves,
+            important_valves,
+            distances,
+            start,
         }
-        let new_remaining_time = remaining_time - time_spent_opening - time_to_destination;
-        let new_steam_so_far =
-            steam_so_far + steam_rate + time_to_destination * increased_steam_rate;
-        let new_opened_so_far = opened_so_far.clone();
-
-        let max_steam_this_path = find_max_flow(
-            volcano,
-            *destination,
-            new_opened_so_far,
-            new_steam_so_far,
-            increased_steam_rate,
-            new_remaining_time,
-        );
-        results.push(max_steam_this_path);
     }
 
-    if results.is_empty() {
-        // We're at the terminal node
-        let remaining_time = if remaining_time > 0 {
-            remaining_time - 1
-        } else {
-            0
-        };
-        if steam_so_far + steam_rate + increased_steam_rate * remaining_time == 2218 {
-            for node in opened_so_far.windows(2) {
-                let source = node[0];
-                let destination = node[1];
-                let source_valve = &volcano.valves[source];
-                let destination_valve = &volcano.valves[destination];
-                let distance = volcano.distances[source][destination];
-                println!(
-                    "Moved {distance} distance from {} to {} to release {}",
-                    source_valve.name, destination_valve.name, destination_valve.steam
-                );
+    /// The most steam each set of important valves can release in `minutes`, indexed by bitmask.
+    /// Sets that can't all be opened in time release 0.
+    fn best_per_subset(&self, minutes: u32) -> Vec<u32> {
+        let mut best = vec![0; 1 << self.important_valves.len()];
+        self.visit(self.start, minutes, 0, 0, &mut best);
+        best
+    }
+
+    // Every valve opened with `remaining` minutes left releases its rate for each of those minutes.
+    fn visit(&self, current: usize, remaining: u32, opened: usize, steam: u32, best: &mut [u32]) {
+        best[opened] = best[opened].max(steam);
+        for (bit, &next) in self.important_valves.iter().enumerate() {
+            let travel_and_open = self.distance(current, next) + 1;
+            if opened & (1 << bit) != 0 || travel_and_open >= remaining {
+                continue;
             }
+            let remaining = remaining - travel_and_open;
+            let steam = steam + remaining * self.valves[next].steam;
+            self.visit(next, remaining, opened | (1 << bit), steam, best);
         }
-        // println!("{:?} - {}", &opened_so_far, steam_so_far + steam_rate + increased_steam_rate * remaining_time);
-        return steam_so_far + steam_rate + increased_steam_rate * remaining_time;
     }
 
-    results.sort_unstable();
-    results[results.len() - 1]
-}
+    /// The best pair of disjoint sets for me and the elephant to open.
+    fn best_split(&self, minutes: u32) -> (Plan, Plan) {
+        let best = self.best_per_subset(minutes);
+        // Best plan using only valves in each set. Built up one valve at a time: the best plan
+        // within a set either leaves out a valve, or it's the set itself.
+        let mut within: Vec<Plan> = best
+            .iter()
+            .enumerate()
+            .map(|(subset, steam)| Plan {
+                subset,
+                steam: *steam,
+            })
+            .collect();
+        for bit in 0..self.important_valves.len() {
+            for subset in 0..within.len() {
+                if subset & (1 << bit) != 0
+                    && within[subset ^ (1 << bit)].steam > within[subset].steam
+                {
+                    within[subset] = within[subset ^ (1 << bit)];
+                }
+            }
+        }
 
-struct Volcano {
-    valves: Vec<Valve>,
-    important_valves: Vec<usize>,
-    distances: Vec<Vec<u32>>,
+        let all = within.len() - 1;
+        (0..within.len())
+            .map(|subset| {
+                let mine = Plan {
+                    subset,
+                    steam: best[subset],
+                };
+                (mine, within[all ^ subset])
+            })
+            .max_by_key(|(mine, elephants)| mine.steam + elephants.steam)
+            .unwrap_or_default()
+    }
+
+    /// The valves in `plan`, in the order that releases `plan.steam`.
+    fn steps(&self, minutes: u32, plan: Plan) -> Vec<Step> {
+        let mut route = Vec::new();
+        self.find_route(self.start, minutes, 0, 0, plan, &mut route);
+
+        let mut from = self.start;
+        let mut total = 0;
+        route
+            .into_iter()
+            .map(|(to, remaining)| {
+                let valve = &self.valves[to];
+                let released = remaining * valve.steam;
+                total += released;
+                let step = Step {
+                    from: self.valves[from].name.clone(),
+                    to: valve.name.clone(),
+                    travel: self.distance(from, to),
+                    minute: minutes - remaining,
+                    rate: valve.steam,
+                    released,
+                    total,
+                };
+                from = to;
+                step
+            })
+            .collect()
+    }
+
+    // Finds the order the valves in the plan were opened in, and how many minutes were left when
+    // each one was opened.
+    fn find_route(
+        &self,
+        current: usize,
+        remaining: u32,
+        opened: usize,
+        steam: u32,
+        plan: Plan,
+        route: &mut Vec<(usize, u32)>,
+    ) -> bool {
+        if opened == plan.subset {
+            return steam == plan.steam;
+        }
+        for (bit, &next) in self.important_valves.iter().enumerate() {
+            let travel_and_open = self.distance(current, next) + 1;
+            if opened & (1 << bit) != 0
+                || plan.subset & (1 << bit) == 0
+                || travel_and_open >= remaining
+            {
+                continue;
+            }
+            let remaining = remaining - travel_and_open;
+            let steam = steam + remaining * self.valves[next].steam;
+            route.push((next, remaining));
+            if self.find_route(next, remaining, opened | (1 << bit), steam, plan, route) {
+                return true;
+            }
+            route.pop();
+        }
+        false
+    }
+
+    fn distance(&self, from: usize, to: usize) -> u32 {
+        self.distances
+            .get(from, to)
+            .expect("every valve can be reached from every other valve")
+    }
 }
 
 #[derive(Debug, Default)]
@@ -152,18 +307,25 @@ fn parse(input: &str) -> Vec<Valve> {
         .flat_map(|mut line| parse_valve(&mut line))
         .collect();
 
-    let mut lookup = AHashMap::with_capacity(valves.len());
+    let mut lookup = Interner::with_capacity(valves.len());
     for (idx, valve) in valves.iter_mut().enumerate() {
         valve.idx = idx;
-        lookup.insert(valve.name.clone(), idx);
     }
-    for valve in valves.iter_mut() {
-        let edge_names: Vec<usize> = valve
-            .edge_names
-            .iter()
-            .filter_map(|edge| lookup.get(edge).copied())
-            .collect();
-        valve.edges = edge_names;
+    for valve in &valves {
+        lookup.intern(&valve.name);
+    }
+    let edges: Vec<Vec<usize>> = valves
+        .iter()
+        .map(|valve| {
+            valve
+                .edge_names
+                .iter()
+                .filter_map(|edge| lookup.id(edge))
+                .collect()
+        })
+        .collect();
+    for (valve, edges) in valves.iter_mut().zip(edges) {
+        valve.edges = edges;
     }
 
     valves
@@ -193,7 +355,132 @@ fn parse_valve(input: &mut &str) -> ModalResult<Valve> {
     }.parse_next(input)
 }
 
-common::aoctest!(1651, 1234, 1234, 1234);
+#[cfg(test)]
+mod plan_tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("test-input.txt");
+
+    // Every step opens a different valve that releases steam, and gets there in time.
+    fn check_routes(input: &str, plan: &ValvePlan) {
+        let volcano = Volcano::new(parse(input));
+        let valve = |name: &str| {
+            volcano
+                .valves
+                .iter()
+                .find(|valve| valve.name == name)
+                .unwrap()
+        };
+        let mut opened = Vec::new();
+        for route in &plan.routes {
+            let mut from = "AA";
+            let mut minute = 0;
+            for step in &route.steps {
+                assert_eq!(step.from, from);
+                assert!(!opened.contains(&step.to), "{} is opened twice", step.to);
+                opened.push(step.to.clone());
+
+                let to = valve(&step.to);
+                assert!(to.steam > 0);
+                assert_eq!(step.rate, to.steam);
+                assert_eq!(step.travel, volcano.distance(valve(from).idx, to.idx));
+                minute += step.travel + 1;
+                assert_eq!(step.minute, minute);
+                assert!(step.minute < plan.minutes);
+                assert_eq!(step.released, (plan.minutes - step.minute) * step.rate);
+                from = &step.to;
+            }
+            let released: u32 = route.steps.iter().map(|step| step.released).sum();
+            assert_eq!(released, route.steam);
+            assert_eq!(route.steps.last().map_or(0, |step| step.total), route.steam);
+        }
+        assert_eq!(
+            plan.routes.iter().map(|route| route.steam).sum::<u32>(),
+            plan.steam
+        );
+    }
+
+    #[test]
+    fn solo_plan() {
+        let plan = plan(INPUT, false);
+        assert_eq!(plan.minutes, 30);
+        assert_eq!(plan.steam, 1651);
+        assert_eq!(plan.routes.len(), 1);
+        check_routes(INPUT, &plan);
+    }
+
+    #[test]
+    fn elephant_plan() {
+        let plan = plan(INPUT, true);
+        assert_eq!(plan.minutes, 26);
+        assert_eq!(plan.steam, 1707);
+        let who: Vec<_> = plan.routes.iter().map(|route| route.who.as_str()).collect();
+        assert_eq!(who, vec!["Me", "Elephant"]);
+        check_routes(INPUT, &plan);
+    }
+
+    #[test]
+    fn input_plans() {
+        let input = include_str!("input.txt");
+        for (elephant, steam) in [(false, 2077), (true, 2741)] {
+            let plan = plan(input, elephant);
+            assert_eq!(plan.steam, steam);
+            check_routes(input, &plan);
+        }
+    }
+
+    #[test]
+    fn explains_as_text() {
+        assert_eq!(
+            explain(INPUT, 1, Format::Text),
+            "1651 steam released in 30 minutes
+Me, releasing 1651
+    minute  2: AA -> DD (1 min), open DD (rate 20) releasing  560, total  560
+    minute  5: DD -> BB (2 min), open BB (rate 13) releasing  325, total  885
+    minute  9: BB -> JJ (3 min), open JJ (rate 21) releasing  441, total 1326
+    minute 17: JJ -> HH (7 min), open HH (rate 22) releasing  286, total 1612
+    minute 21: HH -> EE (3 min), open EE (rate  3) releasing   27, total 1639
+    minute 24: EE -> CC (2 min), open CC (rate  2) releasing   12, total 1651
+"
+        );
+        assert_eq!(
+            explain(INPUT, 2, Format::Text),
+            "1707 steam released in 26 minutes
+Me, releasing 764
+    minute  3: AA -> JJ (2 min), open JJ (rate 21) releasing  483, total  483
+    minute  7: JJ -> BB (3 min), open BB (rate 13) releasing  247, total  730
+    minute  9: BB -> CC (1 min), open CC (rate  2) releasing   34, total  764
+Elephant, releasing 943
+    minute  2: AA -> DD (1 min), open DD (rate 20) releasing  480, total  480
+    minute  7: DD -> HH (4 min), open HH (rate 22) releasing  418, total  898
+    minute 11: HH -> EE (3 min), open EE (rate  3) releasing   45, total  943
+"
+        );
+    }
+
+    #[test]
+    fn explains_as_json() {
+        for part in [1, 2] {
+            let json = explain(INPUT, part, Format::Json);
+            let parsed: ValvePlan = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, plan(INPUT, part == 2));
+            assert_eq!(
+                serde_json::to_string_pretty(&parsed).unwrap(),
+                json,
+                "part {part}"
+            );
+        }
+
+        let json: serde_json::Value =
+            serde_json::from_str(&explain(INPUT, 1, Format::Json)).unwrap();
+        assert_eq!(json["steam"], 1651);
+        assert_eq!(json["routes"][0]["who"], "Me");
+        assert_eq!(json["routes"][0]["steps"][0]["to"], "DD");
+        assert_eq!(json["routes"][0]["steps"][0]["minute"], 2);
+    }
+}
+
+common::aoctest!(1651, 2077, 1707, 2741);
 
 // This doesn't work because of cycles causing exponential run time.
 // It is necessary to visit the same node more than once and even the same edge (source destination pair).
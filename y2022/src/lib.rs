@@ -1 +1,6 @@
 pub mod day16;
+
+common::solutions!(
+    2022;
+    16 => day16 + explain,
+);
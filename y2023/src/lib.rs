@@ -1,2 +1,8 @@
 pub mod day1;
 pub mod day2;
+
+common::solutions!(
+    2023;
+    1 => day1,
+    2 => day2,
+);
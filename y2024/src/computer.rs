@@ -1,9 +1,7 @@
-pub struct Computer<'a> {
-    input: &'a str,
-    pub multiply_enabled: bool,
-}
+use std::io::{self, Read};
+use std::ops::Range;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Instruction {
     Do,
     Dont,
@@ -20,25 +18,231 @@ pub enum Instruction {
     Why,
 }
 
+/// An instruction and where it was found, as byte offsets into the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub instruction: Instruction,
+    pub span: Range<usize>,
+}
+
+/// Splits a string into tokens. Every character ends up in some token, so this never fails.
+pub struct Computer<'a> {
+    input: &'a str,
+    position: usize,
+}
+
 impl<'a> Computer<'a> {
     pub fn init(input: &'a str) -> Computer<'a> {
-        Computer {
-            input,
-            multiply_enabled: true,
-        }
+        Computer { input, position: 0 }
     }
+}
 
-    pub fn next_instruction(&mut self) -> Option<Instruction> {
+impl Iterator for Computer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
         if self.input.is_empty() {
             return None;
         }
-        let (remaining, instruction) = parse_instruction(&mut self.input).unwrap();
+        let (remaining, instruction) = tokenize(self.input);
+        let len = self.input.len() - remaining.len();
+        let span = self.position..self.position + len;
         self.input = remaining;
-        Some(instruction)
+        self.position += len;
+        Some(Token { instruction, span })
+    }
+}
+
+// Longer than any input the parsers look at before giving up or producing a token, apart from
+// runs of whitespace and special characters, which stop before the end of the buffer. The longest
+// is a `mul` with two operands of MAX_DIGITS.
+const LOOKAHEAD: usize = 32;
+const CHUNK_SIZE: usize = 4096;
+
+/// Tokenizes input as it is read, holding on to a few kilobytes at a time.
+/// A token is only produced once enough input follows it that it can't be the start of a longer one,
+/// so the tokens and spans are the same as tokenizing the whole input at once.
+pub struct Stream<R> {
+    reader: R,
+    buffer: String,
+    start: usize,
+    // Bytes of a character split across two reads.
+    partial: Vec<u8>,
+    position: usize,
+    eof: bool,
+}
+
+impl<R: Read> Stream<R> {
+    pub fn new(reader: R) -> Stream<R> {
+        Stream {
+            reader,
+            buffer: String::with_capacity(CHUNK_SIZE + LOOKAHEAD),
+            start: 0,
+            partial: Vec::new(),
+            position: 0,
+            eof: false,
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        self.buffer.drain(..self.start);
+        self.start = 0;
+
+        // Reads until the chunk is full, so that a reader handing out a few bytes at a time doesn't
+        // mean tokenizing a long run again after every read
+        let mut chunk = [0; CHUNK_SIZE];
+        let mut read = 0;
+        while read < CHUNK_SIZE {
+            match self.reader.read(&mut chunk[read..]) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        if read == 0 {
+            self.eof = true;
+            if !self.partial.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "input ends in the middle of a character",
+                ));
+            }
+            return Ok(());
+        }
+
+        self.partial.extend_from_slice(&chunk[..read]);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(_) => self.partial.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        let text = std::str::from_utf8(&self.partial[..valid])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.buffer.push_str(text);
+        self.partial.drain(..valid);
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Stream<R> {
+    type Item = io::Result<Token>;
+
+    fn next(&mut self) -> Option<io::Result<Token>> {
+        loop {
+            let input = &self.buffer[self.start..];
+            if !input.is_empty() {
+                let (remaining, instruction) = tokenize(input);
+                if self.eof || remaining.len() >= LOOKAHEAD {
+                    let len = input.len() - remaining.len();
+                    let span = self.position..self.position + len;
+                    self.start += len;
+                    self.position += len;
+                    return Some(Ok(Token { instruction, span }));
+                }
+            } else if self.eof {
+                return None;
+            }
+            if let Err(e) = self.fill() {
+                // Nothing after an error can be trusted, so the stream ends here
+                self.eof = true;
+                self.buffer.clear();
+                self.start = 0;
+                self.partial.clear();
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// What the program has done so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct State {
+    pub multiply_enabled: bool,
+    pub result: i32,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            multiply_enabled: true,
+            result: 0,
+        }
+    }
+}
+
+/// Gives meaning to tokens. Any `FnMut(&Token, &mut State)` is a handler.
+pub trait Handler {
+    fn handle(&mut self, token: &Token, state: &mut State);
+}
+
+impl<F: FnMut(&Token, &mut State)> Handler for F {
+    fn handle(&mut self, token: &Token, state: &mut State) {
+        self(token, state)
+    }
+}
+
+/// Adds `mul(x,y)` to the result while multiplication is enabled.
+pub fn multiply(token: &Token, state: &mut State) {
+    if let Instruction::Mul(x, y) = token.instruction
+        && state.multiply_enabled
+    {
+        state.result += x * y;
+    }
+}
+
+/// `do()` enables multiplication and `don't()` disables it.
+pub fn conditionals(token: &Token, state: &mut State) {
+    match token.instruction {
+        Instruction::Do => state.multiply_enabled = true,
+        Instruction::Dont => state.multiply_enabled = false,
+        _ => {}
+    }
+}
+
+/// Runs tokens through every handler, in the order the handlers were added.
+/// Tokens no handler cares about are skipped.
+#[derive(Default)]
+pub struct Interpreter<'h> {
+    handlers: Vec<Box<dyn Handler + 'h>>,
+    state: State,
+}
+
+impl<'h> Interpreter<'h> {
+    pub fn new() -> Interpreter<'h> {
+        Interpreter::default()
+    }
+
+    pub fn add_handler(&mut self, handler: impl Handler + 'h) {
+        self.handlers.push(Box::new(handler));
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn run(&mut self, tokens: impl IntoIterator<Item = Token>) -> State {
+        for token in tokens {
+            self.step(&token);
+        }
+        self.state
+    }
+
+    /// Like `run`, reading the program from `reader` as it goes.
+    pub fn run_reader(&mut self, reader: impl Read) -> io::Result<State> {
+        for token in Stream::new(reader) {
+            self.step(&token?);
+        }
+        Ok(self.state)
+    }
+
+    fn step(&mut self, token: &Token) {
+        for handler in &mut self.handlers {
+            handler.handle(token, &mut self.state);
+        }
     }
 }
 
-use winnow::ascii::digit1;
 use winnow::combinator::{alt, delimited, separated_pair};
 use winnow::token::{literal, take_while};
 use winnow::{ModalResult, Parser};
@@ -91,14 +295,19 @@ fn parse_why(input: &mut &str) -> ModalResult<Instruction> {
     literal("why()").map(|_| Instruction::Why).parse_next(input)
 }
 
+// Enough for any i32. Operands are capped so that a `mul` always fits in LOOKAHEAD.
+const MAX_DIGITS: usize = 10;
+
+fn parse_operand(input: &mut &str) -> ModalResult<i32> {
+    take_while(1..=MAX_DIGITS, |c: char| c.is_ascii_digit())
+        .try_map(|s: &str| s.parse::<i32>())
+        .parse_next(input)
+}
+
 fn parse_mul(input: &mut &str) -> ModalResult<Instruction> {
     delimited(
         literal("mul("),
-        separated_pair(
-            digit1.try_map(|s: &str| s.parse::<i32>()),
-            literal(","),
-            digit1.try_map(|s: &str| s.parse::<i32>()),
-        ),
+        separated_pair(parse_operand, literal(","), parse_operand),
         literal(")"),
     )
     .map(|(x, y)| Instruction::Mul(x, y))
@@ -134,6 +343,21 @@ fn is_whitespace(c: char) -> bool {
     c == ' ' || c == '\t' || c == '\n'
 }
 
+fn tokenize(input: &str) -> (&str, Instruction) {
+    let mut cursor = input;
+    match parse_instruction(&mut cursor) {
+        Ok(parsed) => parsed,
+        // parse_gibberish takes any character, so this only happens if there's no input left
+        Err(_) => {
+            let mut chars = input.chars();
+            let instruction = chars
+                .next()
+                .map_or(Instruction::Whitespace, Instruction::UnknownChar);
+            (chars.as_str(), instruction)
+        }
+    }
+}
+
 fn parse_instruction<'s>(input: &mut &'s str) -> ModalResult<(&'s str, Instruction)> {
     alt((
         parse_do,
@@ -152,3 +376,117 @@ fn parse_instruction<'s>(input: &mut &'s str) -> ModalResult<(&'s str, Instructi
     ))
     .parse_peek(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FULL_INPUT: &str = include_str!("day3/input.txt");
+
+    // Hands out at most `size` bytes per read.
+    struct Chunked<'a> {
+        input: &'a [u8],
+        size: usize,
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.size.min(buf.len()).min(self.input.len());
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+            Ok(len)
+        }
+    }
+
+    const READ_SIZES: [usize; 4] = [1, 7, CHUNK_SIZE - 1, CHUNK_SIZE];
+
+    fn assert_streams_like_computer(input: &str, sizes: &[usize]) {
+        let expected: Vec<Token> = Computer::init(input).collect();
+        for &size in sizes {
+            let reader = Chunked {
+                input: input.as_bytes(),
+                size,
+            };
+            let streamed: Vec<Token> = Stream::new(reader)
+                .collect::<io::Result<_>>()
+                .expect("valid UTF-8");
+            assert_eq!(streamed, expected, "reading {size} bytes at a time");
+        }
+    }
+
+    // `token` with padding in front, so that a chunk boundary falls at every point in it.
+    fn across_boundaries(token: &str) -> impl Iterator<Item = String> {
+        (CHUNK_SIZE - token.len() - 2..=CHUNK_SIZE + 1).map(move |padding| {
+            let mut input = " ".repeat(padding);
+            input.push_str(token);
+            input.push_str("do()");
+            input
+        })
+    }
+
+    #[test]
+    fn streams_the_input() {
+        assert_streams_like_computer(FULL_INPUT, &READ_SIZES);
+    }
+
+    #[test]
+    fn streams_long_tokens() {
+        let longest = format!(
+            "mul({},{})",
+            "1".repeat(MAX_DIGITS),
+            "2".repeat(MAX_DIGITS - 1)
+        );
+        assert_eq!(
+            Computer::init(&longest).next().unwrap().instruction,
+            Instruction::Mul(1111111111, 222222222)
+        );
+        let too_long = format!("mul({}1,2)", "0".repeat(40));
+        assert_eq!(
+            Computer::init(&too_long).next().unwrap().instruction,
+            Instruction::UnknownChar('m')
+        );
+        for token in [
+            longest.as_str(),
+            too_long.as_str(),
+            "mul(0000000001,0000000002)",
+        ] {
+            assert_streams_like_computer(token, &READ_SIZES);
+            for input in across_boundaries(token) {
+                assert_streams_like_computer(&input, &[CHUNK_SIZE]);
+            }
+        }
+    }
+
+    #[test]
+    fn streams_characters_split_across_reads() {
+        let input = "é€😀mul(2,3)😀".repeat(1000);
+        assert_streams_like_computer(&input, &READ_SIZES);
+        for input in across_boundaries("😀") {
+            assert_streams_like_computer(&input, &[CHUNK_SIZE]);
+        }
+    }
+
+    #[test]
+    fn streams_runs_of_whitespace_and_special_characters() {
+        let input = format!(
+            "mul(1,2){}mul(3,4){}don't()",
+            " \t\n".repeat(3000),
+            "!@#[]".repeat(2000)
+        );
+        assert_streams_like_computer(&input, &READ_SIZES);
+        let tokens: Vec<Token> = Computer::init(&input).collect();
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[1].instruction, Instruction::Whitespace);
+        assert_eq!(tokens[1].span, 8..9008);
+    }
+
+    #[test]
+    fn rejects_invalid_utf8() {
+        let reader = Chunked {
+            input: b"mul(1,2)\xff",
+            size: 1,
+        };
+        let tokens: Vec<_> = Stream::new(reader).collect();
+        assert!(tokens.last().unwrap().is_err());
+    }
+}
@@ -0,0 +1,503 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ahash::AHashMap;
+use common::math::num_digits;
+
+/// Which stones a rule applies to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Any,
+    Equals(u64),
+    EvenDigits,
+    OddDigits,
+    DivisibleBy(u64),
+}
+
+/// What a rule does to a stone.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    Replace(u64),
+    /// Into the left and right halves of its digits. With an odd number of digits the left half
+    /// gets the extra digit, and a single digit splits into itself and 0.
+    Split,
+    Multiply(u64),
+    Add(u64),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub condition: Condition,
+    pub action: Action,
+}
+
+/// What happens to each stone when blinking. The first rule whose condition matches is applied,
+/// and a stone no rule matches stays as it is.
+///
+/// Rules are written one per line as `condition -> action`:
+/// ```text
+/// 0 -> 1
+/// even digits -> split
+/// any -> * 2024
+/// ```
+/// Conditions are `any`, a number, `even digits`, `odd digits` or `divisible by` a number.
+/// Actions are a number to replace the stone with, `split`, `* n` or `+ n`.
+/// Blank lines and anything after a `#` are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+/// The rules from the puzzle.
+pub const PUZZLE: &str = "0 -> 1\neven digits -> split\nany -> * 2024";
+
+/// The stones a single stone turns into.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stones {
+    One(u64),
+    Two(u64, u64),
+}
+
+/// A number of stones. Counts grow by about half each generation with the puzzle's rules, so u64
+/// overflows after about 100 generations and u128 after about 200. f64 gives an estimate for up
+/// to about 1,600 generations, after which it overflows to infinity.
+pub trait Count: Copy {
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// None if the sum is too big to count.
+    fn checked_add(self, other: Self) -> Option<Self>;
+}
+
+impl Count for u64 {
+    const ZERO: u64 = 0;
+    const ONE: u64 = 1;
+
+    fn checked_add(self, other: u64) -> Option<u64> {
+        u64::checked_add(self, other)
+    }
+}
+
+impl Count for u128 {
+    const ZERO: u128 = 0;
+    const ONE: u128 = 1;
+
+    fn checked_add(self, other: u128) -> Option<u128> {
+        u128::checked_add(self, other)
+    }
+}
+
+impl Count for f64 {
+    const ZERO: f64 = 0.0;
+    const ONE: f64 = 1.0;
+
+    fn checked_add(self, other: f64) -> Option<f64> {
+        let sum = self + other;
+        sum.is_finite().then_some(sum)
+    }
+}
+
+impl Condition {
+    fn matches(self, stone: u64) -> bool {
+        match self {
+            Condition::Any => true,
+            Condition::Equals(n) => stone == n,
+            Condition::EvenDigits => num_digits(stone).is_multiple_of(2),
+            Condition::OddDigits => !num_digits(stone).is_multiple_of(2),
+            Condition::DivisibleBy(n) => stone.is_multiple_of(n),
+        }
+    }
+}
+
+impl Action {
+    /// Panics if the new number doesn't fit in a u64.
+    fn apply(self, stone: u64) -> Stones {
+        match self {
+            Action::Replace(n) => Stones::One(n),
+            Action::Split => {
+                let power = 10u64.pow(num_digits(stone) / 2);
+                Stones::Two(stone / power, stone % power)
+            }
+            Action::Multiply(n) => Stones::One(stone.checked_mul(n).expect("stone overflowed")),
+            Action::Add(n) => Stones::One(stone.checked_add(n).expect("stone overflowed")),
+        }
+    }
+}
+
+impl Rules {
+    pub fn new(rules: Vec<Rule>) -> Rules {
+        Rules { rules }
+    }
+
+    pub fn puzzle() -> Rules {
+        PUZZLE.parse().expect("the puzzle's rules parse")
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn blink(&self, stone: u64) -> Stones {
+        self.rules
+            .iter()
+            .find(|rule| rule.condition.matches(stone))
+            .map_or(Stones::One(stone), |rule| rule.action.apply(stone))
+    }
+
+    /// Number of stones `stone` turns into after `generations` blinks, recursing once per blink.
+    /// `memoized` can be shared between calls with the same rules.
+    pub fn count(
+        &self,
+        stone: u64,
+        generations: u64,
+        memoized: &mut AHashMap<(u64, u64), u64>,
+    ) -> u64 {
+        // Leaf, no more generations
+        if generations == 0 {
+            return 1;
+        }
+        if let Some(answer) = memoized.get(&(stone, generations)) {
+            return *answer;
+        }
+        let progeny = match self.blink(stone) {
+            Stones::One(stone) => self.count(stone, generations - 1, memoized),
+            Stones::Two(left, right) => {
+                self.count(left, generations - 1, memoized)
+                    + self.count(right, generations - 1, memoized)
+            }
+        };
+        memoized.insert((stone, generations), progeny);
+        progeny
+    }
+
+    /// How many stones there are, generation after generation, starting with the generation after
+    /// `stones`. Only the distinct numbers are blinked, so this copes with thousands of generations
+    /// as long as the numbers keep repeating. It ends at the first generation with more stones than
+    /// `C` can count.
+    pub fn evolve<C: Count>(&self, stones: impl IntoIterator<Item = u64>) -> Evolution<'_, C> {
+        let mut counts = AHashMap::new();
+        for stone in stones {
+            add(&mut counts, stone, C::ONE).expect("one of each stone can be counted");
+        }
+        Evolution {
+            rules: self,
+            counts,
+            overflowed: false,
+        }
+    }
+}
+
+/// Iterator over the total number of stones in each generation, from `Rules::evolve`.
+/// `counts` has the stones of each number in the generation the iterator last returned.
+pub struct Evolution<'a, C> {
+    rules: &'a Rules,
+    counts: AHashMap<u64, C>,
+    overflowed: bool,
+}
+
+impl<C> Evolution<'_, C> {
+    pub fn counts(&self) -> &AHashMap<u64, C> {
+        &self.counts
+    }
+}
+
+impl<C: Count> Iterator for Evolution<'_, C> {
+    type Item = C;
+
+    fn next(&mut self) -> Option<C> {
+        if self.overflowed {
+            return None;
+        }
+        let Some((counts, total)) = self.blink() else {
+            self.overflowed = true;
+            return None;
+        };
+        self.counts = counts;
+        Some(total)
+    }
+}
+
+impl<C: Count> Evolution<'_, C> {
+    // The next generation and its total, or None if a count overflows.
+    fn blink(&self) -> Option<(AHashMap<u64, C>, C)> {
+        let mut next = AHashMap::with_capacity(self.counts.len());
+        for (stone, count) in &self.counts {
+            match self.rules.blink(*stone) {
+                Stones::One(stone) => add(&mut next, stone, *count)?,
+                Stones::Two(left, right) => {
+                    add(&mut next, left, *count)?;
+                    add(&mut next, right, *count)?;
+                }
+            }
+        }
+        let total = next
+            .values()
+            .try_fold(C::ZERO, |total, count| total.checked_add(*count))?;
+        Some((next, total))
+    }
+}
+
+fn add<C: Count>(counts: &mut AHashMap<u64, C>, stone: u64, count: C) -> Option<()> {
+    let total = counts.entry(stone).or_insert(C::ZERO);
+    *total = total.checked_add(count)?;
+    Some(())
+}
+
+impl FromStr for Rules {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Rules> {
+        let rules = s
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let Some((condition, action)) = line.split_once("->") else {
+                    anyhow::bail!("Expected `condition -> action`, found {line:?}");
+                };
+                Ok(Rule {
+                    condition: condition.trim().parse()?,
+                    action: action.trim().parse()?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Rules { rules })
+    }
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Condition> {
+        let words: Vec<_> = s.split_ascii_whitespace().collect();
+        Ok(match words[..] {
+            ["any"] => Condition::Any,
+            ["even", "digits"] => Condition::EvenDigits,
+            ["odd", "digits"] => Condition::OddDigits,
+            ["divisible", "by", n] => match n.parse()? {
+                0 => anyhow::bail!("Nothing is divisible by 0"),
+                n => Condition::DivisibleBy(n),
+            },
+            [n] => Condition::Equals(
+                n.parse()
+                    .map_err(|_| anyhow::anyhow!("Unknown condition {s:?}"))?,
+            ),
+            _ => anyhow::bail!("Unknown condition {s:?}"),
+        })
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Action> {
+        let words: Vec<_> = s.split_ascii_whitespace().collect();
+        Ok(match words[..] {
+            ["split"] => Action::Split,
+            ["*", n] => Action::Multiply(n.parse()?),
+            ["+", n] => Action::Add(n.parse()?),
+            [n] => Action::Replace(
+                n.parse()
+                    .map_err(|_| anyhow::anyhow!("Unknown action {s:?}"))?,
+            ),
+            _ => anyhow::bail!("Unknown action {s:?}"),
+        })
+    }
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rule in &self.rules {
+            writeln!(f, "{rule}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.condition {
+            Condition::Any => write!(f, "any")?,
+            Condition::Equals(n) => write!(f, "{n}")?,
+            Condition::EvenDigits => write!(f, "even digits")?,
+            Condition::OddDigits => write!(f, "odd digits")?,
+            Condition::DivisibleBy(n) => write!(f, "divisible by {n}")?,
+        }
+        write!(f, " -> ")?;
+        match self.action {
+            Action::Replace(n) => write!(f, "{n}"),
+            Action::Split => write!(f, "split"),
+            Action::Multiply(n) => write!(f, "* {n}"),
+            Action::Add(n) => write!(f, "+ {n}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [u64; 2] = [125, 17];
+
+    fn counts(rules: &Rules, stones: &[u64], generations: u64) -> Vec<u64> {
+        let mut memoized = AHashMap::new();
+        (1..=generations)
+            .map(|generation| {
+                stones
+                    .iter()
+                    .map(|stone| rules.count(*stone, generation, &mut memoized))
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_and_prints_rules() {
+        let rules: Rules = "
+            # Comments and blank lines are skipped
+
+            0 -> 1
+            divisible by 3 -> + 7   # trailing comments too
+            odd digits -> split
+            even digits -> * 3
+            any -> 5
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(
+            rules.rules(),
+            &[
+                Rule {
+                    condition: Condition::Equals(0),
+                    action: Action::Replace(1)
+                },
+                Rule {
+                    condition: Condition::DivisibleBy(3),
+                    action: Action::Add(7)
+                },
+                Rule {
+                    condition: Condition::OddDigits,
+                    action: Action::Split
+                },
+                Rule {
+                    condition: Condition::EvenDigits,
+                    action: Action::Multiply(3)
+                },
+                Rule {
+                    condition: Condition::Any,
+                    action: Action::Replace(5)
+                },
+            ]
+        );
+        let printed = rules.to_string();
+        assert_eq!(
+            printed,
+            "0 -> 1\ndivisible by 3 -> + 7\nodd digits -> split\neven digits -> * 3\nany -> 5\n"
+        );
+        assert_eq!(printed.parse::<Rules>().unwrap(), rules);
+        assert_eq!(Rules::puzzle().to_string().trim_end(), PUZZLE);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let error = |rules: &str| rules.parse::<Rules>().unwrap_err().to_string();
+        assert_eq!(
+            error("0 1"),
+            "Expected `condition -> action`, found \"0 1\""
+        );
+        assert_eq!(
+            error("some digits -> 1"),
+            "Unknown condition \"some digits\""
+        );
+        assert_eq!(error("divisible by 0 -> 1"), "Nothing is divisible by 0");
+        assert_eq!(error("any -> / 2"), "Unknown action \"/ 2\"");
+        assert_eq!(error("any -> merge"), "Unknown action \"merge\"");
+    }
+
+    #[test]
+    fn splits_odd_digits() {
+        let rules: Rules = "odd digits -> split".parse().unwrap();
+        assert_eq!(rules.blink(12345), Stones::Two(123, 45));
+        assert_eq!(rules.blink(100), Stones::Two(10, 0));
+        assert_eq!(rules.blink(7), Stones::Two(7, 0));
+        // No rule matches, so the stone stays as it is
+        assert_eq!(rules.blink(1234), Stones::One(1234));
+    }
+
+    #[test]
+    fn first_matching_rule_applies() {
+        let rules: Rules = "divisible by 2 -> + 1\nany -> * 2".parse().unwrap();
+        assert_eq!(rules.blink(4), Stones::One(5));
+        assert_eq!(rules.blink(5), Stones::One(10));
+        // Alternates between doubling and adding one, without ever splitting
+        assert_eq!(counts(&rules, &[1], 10), vec![1; 10]);
+    }
+
+    #[test]
+    fn puzzle_example() {
+        let rules = Rules::puzzle();
+        let evolved: Vec<u64> = rules.evolve(EXAMPLE).take(6).collect();
+        assert_eq!(evolved, vec![3, 4, 5, 9, 13, 22]);
+
+        let mut evolution = rules.evolve::<u64>(EXAMPLE);
+        evolution.nth(5);
+        let mut stones: Vec<_> = evolution.counts().iter().collect();
+        stones.sort_unstable();
+        assert_eq!(stones[..3], [(&0, &2), (&2, &4), (&3, &1)]);
+        assert_eq!(evolution.counts().values().sum::<u64>(), 22);
+    }
+
+    #[test]
+    fn evolve_agrees_with_count() {
+        let custom: Rules = "0 -> 1\nodd digits -> split\ndivisible by 7 -> 0\nany -> * 3"
+            .parse()
+            .unwrap();
+        for rules in [Rules::puzzle(), custom] {
+            let stones = [0, 1, 7, 125, 17, 2024, 99999];
+            let expected = counts(&rules, &stones, 40);
+            let evolved: Vec<u64> = rules.evolve(stones).take(40).collect();
+            assert_eq!(evolved, expected, "{rules}");
+
+            let wide: Vec<u128> = rules.evolve(stones).take(40).collect();
+            assert!(wide.iter().zip(&expected).all(|(w, e)| *w == *e as u128));
+            let estimate: Vec<f64> = rules.evolve(stones).take(40).collect();
+            assert!(estimate.iter().zip(&expected).all(|(f, e)| *f == *e as f64));
+        }
+    }
+
+    #[test]
+    fn stops_when_counts_overflow() {
+        // Every stone splits into itself and 0, so the count doubles every generation
+        let rules: Rules = "any -> split".parse().unwrap();
+        let totals: Vec<u64> = rules.evolve([0]).collect();
+        assert_eq!(totals.len(), 63);
+        assert_eq!(totals.last(), Some(&(1 << 63)));
+
+        let mut evolution = rules.evolve::<u64>([0]);
+        assert_eq!(evolution.nth(62), Some(1 << 63));
+        assert_eq!(evolution.next(), None);
+        assert_eq!(evolution.next(), None);
+        // The counts are still the last generation that fit
+        assert_eq!(evolution.counts()[&0], 1 << 63);
+
+        assert_eq!(rules.evolve::<u128>([0]).count(), 127);
+        assert_eq!(rules.evolve::<f64>([0]).count(), 1023);
+    }
+
+    #[test]
+    fn puzzle_rules_overflow_u64() {
+        let rules = Rules::puzzle();
+        let narrow: Vec<u64> = rules.evolve([125, 17]).collect();
+        let wide: Vec<u128> = rules.evolve([125, 17]).take(narrow.len() + 1).collect();
+        assert!(narrow.iter().zip(&wide).all(|(n, w)| *n as u128 == *w));
+        assert!(wide[narrow.len()] > u64::MAX as u128);
+    }
+
+    #[test]
+    fn empty_input_has_no_stones() {
+        let rules = Rules::puzzle();
+        let mut evolution = rules.evolve::<u64>([]);
+        assert_eq!(evolution.next(), Some(0));
+        assert!(evolution.counts().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use num::integer::lcm;
+use common::linear;
 
 /// Not on performance: Cramer's rule (229 µs, 224 µs) slightly outpeforms the Naive version (233 µs, 233 µs).
 /// Parallelising this regresses performance to 286µs (+22%) and 278µs (+23%).
@@ -12,9 +12,7 @@ use num::integer::lcm;
 /// Note that benching the parse() function only works if it returns a Vec<_>, not an impl Iterator, which returns within a few ns.
 #[inline]
 pub fn part1(input: &str) -> i64 {
-    parse(input)
-        .filter_map(|(eq1, eq2)| solve_cramer_rule(eq1, eq2))
-        .sum()
+    parse(input).filter_map(|(eq1, eq2)| tokens(eq1, eq2)).sum()
 }
 
 #[inline]
@@ -34,43 +32,33 @@ pub fn part2(input: &str) -> i64 {
                 },
             )
         })
-        .filter_map(|(eq1, eq2)| solve_cramer_rule(eq1, eq2))
+        .filter_map(|(eq1, eq2)| tokens(eq1, eq2))
         .sum()
 }
 
-/// Note on correctness: the input with 10000000009164 and 10000000002799 gave the wrong answer
-/// on the first version of this function. It incorrectly assumed that if dividend % divisor == 0
-/// then a solution exists.
-#[allow(dead_code)]
-fn solve_naive(one: Equation, two: Equation) -> Option<i64> {
-    let lcm = lcm(one.op1, two.op1);
-    let mul_one = lcm / one.op1;
-    let mul_two = lcm / two.op1;
-    let dividend = one.result * mul_one - two.result * mul_two;
-    let divisor = one.op2 * mul_one - two.op2 * mul_two;
-    if divisor == 0 {
-        return None;
-    }
-    let b = dividend / divisor;
-    let a = (one.result - one.op2 * b) / one.op1;
-    if (one.op1 * a + one.op2 * b, two.op1 * a + two.op2 * b) == (one.result, two.result) {
-        Some(a * 3 + b)
-    } else {
-        None
-    }
-}
-
 /// Thanks to https://old.reddit.com/r/adventofcode/comments/1hd7irq/2024_day_13_an_explanation_of_the_mathematics/
 /// for explaining.
-fn solve_cramer_rule(one: Equation, two: Equation) -> Option<i64> {
+/// When the buttons move the claw in the same direction there's no single answer, and the
+/// general solver finds the cheapest way to reach the prize.
+fn tokens(one: Equation, two: Equation) -> Option<i64> {
     let det = one.op1 * two.op2 - one.op2 * two.op1;
-    let a = (one.result * two.op2 - two.result * one.op2) / det;
-    let b = (one.op1 * two.result - two.op1 * one.result) / det;
-    if (one.op1 * a + one.op2 * b, two.op1 * a + two.op2 * b) == (one.result, two.result) {
-        Some(a * 3 + b)
-    } else {
-        None
+    let buttons = [[one.op1, one.op2], [two.op1, two.op2]];
+    // Buttons that don't move the claw only win a prize that's where the claw starts, for free
+    if buttons.iter().flatten().all(|op| *op == 0) {
+        return (one.result == 0 && two.result == 0).then_some(0);
     }
+    let (a, b) = if det == 0 {
+        let presses = linear::min_cost_non_negative(&buttons, &[one.result, two.result], &[3, 1])
+            .expect("a button that moves leaves at most one free variable")?;
+        (presses[0], presses[1])
+    } else {
+        (
+            (one.result * two.op2 - two.result * one.op2) / det,
+            (one.op1 * two.result - two.op1 * one.result) / det,
+        )
+    };
+    let solved = (one.op1 * a + one.op2 * b, two.op1 * a + two.op2 * b) == (one.result, two.result);
+    (solved && a >= 0 && b >= 0).then_some(a * 3 + b)
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -161,4 +149,42 @@ fn parse_prize(input: &mut &str) -> ModalResult<(i64, i64)> {
     .parse_next(input)
 }
 
+#[cfg(test)]
+mod token_tests {
+    use super::*;
+
+    fn tokens_for(a: (i64, i64), b: (i64, i64), prize: (i64, i64)) -> Option<i64> {
+        tokens(
+            Equation {
+                op1: a.0,
+                op2: b.0,
+                result: prize.0,
+            },
+            Equation {
+                op1: a.1,
+                op2: b.1,
+                result: prize.1,
+            },
+        )
+    }
+
+    #[test]
+    fn parallel_buttons() {
+        // 2 A and 2 B, instead of 10 B or 1 A and 6 B
+        assert_eq!(tokens_for((4, 4), (1, 1), (10, 10)), Some(8));
+        // A moves twice as far for three times the cost, so it's only B
+        assert_eq!(tokens_for((2, 2), (1, 1), (10, 10)), Some(10));
+        assert_eq!(tokens_for((2, 2), (4, 4), (7, 7)), None);
+        // Along the line the buttons move, but not to the prize
+        assert_eq!(tokens_for((2, 2), (1, 1), (10, 11)), None);
+        assert_eq!(tokens_for((0, 0), (2, 3), (4, 6)), Some(2));
+    }
+
+    #[test]
+    fn buttons_that_dont_move() {
+        assert_eq!(tokens_for((0, 0), (0, 0), (0, 0)), Some(0));
+        assert_eq!(tokens_for((0, 0), (0, 0), (1, 0)), None);
+    }
+}
+
 common::aoctest!(480, 25751, 875318608908, 108528956728655);
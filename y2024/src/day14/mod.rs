@@ -87,7 +87,9 @@ pub fn part2(input: &str) -> i32 {
     // We need to calculate n - the number of iterations.
     // We also know that n % rows = min_x_variance and n % columns = min_y_variance
     // We can use Chinese Remainder Theorem to find the value of n
-    chinese_remainder_theorem(min_x_variance, rows, min_y_variance, columns).unwrap()
+    common::math::crt(&[(min_x_variance, rows), (min_y_variance, columns)])
+        .unwrap()
+        .residue
 }
 
 // This function doesn't bother with the division by n and sqrt because we're only using it for sorting
@@ -100,58 +102,6 @@ fn get_variance(positions: &[i32]) -> f64 {
         .sum::<f64>()
 }
 
-fn extended_gcd(a: i32, b: i32) -> (i32, i32, i32) {
-    if a == 0 {
-        return (b, 0, 1);
-    }
-
-    let (gcd, x1, y1) = extended_gcd(b % a, a);
-    let x = y1 - (b / a) * x1;
-    let y = x1;
-
-    (gcd, x, y)
-}
-
-fn mod_inverse(a: i32, m: i32) -> Option<i32> {
-    let (gcd, x, _) = extended_gcd(a, m);
-
-    if gcd != 1 {
-        // Modular multiplicative inverse doesn't exist
-        return None;
-    }
-
-    // Make sure the result is positive
-    Some((x % m + m) % m)
-}
-
-fn chinese_remainder_theorem(a1: i32, n1: i32, a2: i32, n2: i32) -> Option<i32> {
-    // Check if moduli are coprime
-    let (gcd, _, _) = extended_gcd(n1, n2);
-    if gcd != 1 {
-        return None;
-    }
-
-    let n = n1 * n2;
-
-    // Calculate N1 and N2
-    let n1_part = n2;
-    let n2_part = n1;
-
-    // Calculate x1 and x2 (modular multiplicative inverses)
-    let x1 = mod_inverse(n1_part, n1)?;
-    let x2 = mod_inverse(n2_part, n2)?;
-
-    // Calculate result using CRT formula
-    let mut result = (a1 * n1_part * x1 + a2 * n2_part * x2) % n;
-
-    // Make sure the result is positive
-    if result < 0 {
-        result += n;
-    }
-
-    Some(result)
-}
-
 #[allow(dead_code)]
 fn print_grid(robots: &[Robot], rows: i32, columns: i32) {
     let locations = get_robot_locations(robots);
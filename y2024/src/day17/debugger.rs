@@ -0,0 +1,349 @@
+use std::fmt::{self, Write};
+
+use anyhow::Context;
+
+use super::{Combo, Computer, Instruction};
+
+/// Prints the program one instruction per line, with what each instruction does.
+/// `2,4,1,1` becomes
+/// ```text
+///  0: bst A    B = A % 8
+///  2: bxl 1    B = B ^ 1
+/// ```
+pub fn disassemble(program: &[u64]) -> anyhow::Result<String> {
+    let mut output = String::new();
+    for (idx, chunk) in program.chunks_exact(2).enumerate() {
+        let instruction = Instruction::decode(chunk[0], chunk[1])
+            .with_context(|| format!("Couldn't decode the instruction at {}", idx * 2))?;
+        let mnemonic = instruction.to_string();
+        writeln!(
+            output,
+            "{:>2}: {mnemonic:<8} {}",
+            idx * 2,
+            instruction.describe()
+        )
+        .unwrap();
+    }
+    Ok(output)
+}
+
+impl Computer {
+    pub fn disassemble(&self) -> anyhow::Result<String> {
+        disassemble(&self.mem)
+    }
+}
+
+impl Instruction {
+    /// The effect of the instruction, written out with the combo operand resolved.
+    pub fn describe(&self) -> String {
+        match self {
+            Instruction::Adv(combo) => format!("A = A >> {combo}"),
+            Instruction::Bxl(literal) => format!("B = B ^ {literal}"),
+            Instruction::Bst(combo) => format!("B = {combo} % 8"),
+            Instruction::Jnz(target) => format!("jump to {target} if A != 0"),
+            Instruction::Bxc => "B = B ^ C".to_string(),
+            Instruction::Out(combo) => format!("output {combo} % 8"),
+            Instruction::Bdv(combo) => format!("B = A >> {combo}"),
+            Instruction::Cdv(combo) => format!("C = A >> {combo}"),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Adv(combo) => write!(f, "adv {combo}"),
+            Instruction::Bxl(literal) => write!(f, "bxl {literal}"),
+            Instruction::Bst(combo) => write!(f, "bst {combo}"),
+            Instruction::Jnz(target) => write!(f, "jnz {target}"),
+            Instruction::Bxc => write!(f, "bxc"),
+            Instruction::Out(combo) => write!(f, "out {combo}"),
+            Instruction::Bdv(combo) => write!(f, "bdv {combo}"),
+            Instruction::Cdv(combo) => write!(f, "cdv {combo}"),
+        }
+    }
+}
+
+impl fmt::Display for Combo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Combo::Literal(n) => write!(f, "{n}"),
+            Combo::A => f.write_char('A'),
+            Combo::B => f.write_char('B'),
+            Combo::C => f.write_char('C'),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Registers {
+    pub a: u64,
+    pub b: u64,
+    pub c: u64,
+}
+
+/// One executed instruction, with the registers as they were after it ran.
+#[derive(Debug, Copy, Clone)]
+pub struct Step {
+    pub instruction_pointer: usize,
+    pub instruction: Instruction,
+    pub registers: Registers,
+    pub output: Option<u64>,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Registers { a, b, c } = self.registers;
+        write!(
+            f,
+            "{:>2}: {:<8} A={a:<16} B={b:<16} C={c}",
+            self.instruction_pointer,
+            self.instruction.to_string()
+        )?;
+        if let Some(output) = self.output {
+            write!(f, " out {output}")?;
+        }
+        Ok(())
+    }
+}
+
+pub enum Breakpoint {
+    /// Stops before the instruction at this position runs.
+    InstructionPointer(usize),
+    /// Stops before the next instruction runs if the registers match.
+    Condition(Box<dyn Fn(&Registers) -> bool>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Halted,
+    /// Index of the breakpoint that was hit, in the order they were added.
+    Breakpoint(usize),
+}
+
+/// Runs a `Computer` one instruction at a time, recording every step.
+pub struct Debugger {
+    computer: Computer,
+    breakpoints: Vec<Breakpoint>,
+    trace: Vec<Step>,
+}
+
+impl Debugger {
+    pub fn new(computer: Computer) -> Debugger {
+        Debugger {
+            computer,
+            breakpoints: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            a: self.computer.A,
+            b: self.computer.B,
+            c: self.computer.C,
+        }
+    }
+
+    pub fn trace(&self) -> &[Step] {
+        &self.trace
+    }
+
+    pub fn output(&self) -> Vec<u64> {
+        self.trace.iter().filter_map(|step| step.output).collect()
+    }
+
+    /// Runs one instruction. Returns None once the program has halted.
+    /// An instruction that can't be decoded is an error, and leaves the computer as it was.
+    pub fn step(&mut self) -> anyhow::Result<Option<&Step>> {
+        if !self.computer.is_instruction_pointer_valid() {
+            return Ok(None);
+        }
+        let instruction_pointer = self.computer.instruction_pointer;
+        let instruction = self.computer.current_instruction()?;
+        let output = self.computer.execute_one(instruction);
+        self.trace.push(Step {
+            instruction_pointer,
+            instruction,
+            registers: self.registers(),
+            output,
+        });
+        Ok(self.trace.last())
+    }
+
+    /// Runs until the program halts, a breakpoint is hit or an instruction can't be decoded.
+    /// At least one instruction runs, so calling this again resumes past the breakpoint.
+    pub fn run(&mut self) -> anyhow::Result<Stop> {
+        if self.step()?.is_none() {
+            return Ok(Stop::Halted);
+        }
+        while self.computer.is_instruction_pointer_valid() {
+            if let Some(idx) = self.hit_breakpoint() {
+                return Ok(Stop::Breakpoint(idx));
+            }
+            self.step()?;
+        }
+        Ok(Stop::Halted)
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.computer.instruction_pointer
+    }
+
+    fn hit_breakpoint(&self) -> Option<usize> {
+        let registers = self.registers();
+        self.breakpoints
+            .iter()
+            .position(|breakpoint| match breakpoint {
+                Breakpoint::InstructionPointer(ip) => *ip == self.computer.instruction_pointer,
+                Breakpoint::Condition(condition) => condition(&registers),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = include_str!("test-input.txt");
+
+    // The part 1 example
+    const OUTPUT_EXAMPLE: &str = "Register A: 729
+Register B: 0
+Register C: 0
+
+Program: 0,1,5,4,3,0";
+
+    #[test]
+    fn disassembles_example() {
+        let computer = super::super::parse(INPUT);
+        assert_eq!(
+            computer.disassemble().unwrap(),
+            " 0: adv 3    A = A >> 3
+ 2: out A    output A % 8
+ 4: jnz 0    jump to 0 if A != 0
+"
+        );
+    }
+
+    #[test]
+    fn disassembles_combo_registers() {
+        assert_eq!(
+            disassemble(&[2, 4, 1, 7, 7, 5, 4, 0, 6, 6]).unwrap(),
+            " 0: bst A    B = A % 8
+ 2: bxl 7    B = B ^ 7
+ 4: cdv B    C = A >> B
+ 6: bxc      B = B ^ C
+ 8: bdv C    B = A >> C
+"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_combo_operand() {
+        // A literal operand of 7 is fine, a combo operand of 7 isn't
+        assert!(disassemble(&[1, 7, 3, 7]).is_ok());
+        for opcode in [0, 2, 5, 6, 7] {
+            let err = disassemble(&[1, 7, opcode, 7]).unwrap_err();
+            assert_eq!(
+                format!("{err:#}"),
+                "Couldn't decode the instruction at 2: Invalid combo operand 7"
+            );
+        }
+        assert!(disassemble(&[8, 0]).is_err());
+    }
+
+    #[test]
+    fn trace_matches_execution() {
+        let computer = super::super::parse(OUTPUT_EXAMPLE);
+        let mut debugger = Debugger::new(computer.clone());
+        assert_eq!(debugger.run().unwrap(), Stop::Halted);
+        assert_eq!(debugger.output(), vec![4, 6, 3, 5, 6, 3, 5, 2, 1, 0]);
+        assert_eq!(debugger.output(), computer.clone().execute().unwrap());
+
+        // Each iteration runs adv, out and jnz, and the last jump falls through
+        assert_eq!(debugger.trace().len(), 30);
+        let first = debugger.trace()[0];
+        assert_eq!(first.instruction_pointer, 0);
+        assert_eq!(first.instruction, Instruction::Adv(Combo::Literal(1)));
+        assert_eq!(first.registers, Registers { a: 364, b: 0, c: 0 });
+        assert_eq!(first.output, None);
+        assert_eq!(debugger.trace()[1].output, Some(4));
+        assert_eq!(debugger.registers(), Registers { a: 0, b: 0, c: 0 });
+
+        assert!(debugger.step().unwrap().is_none());
+        assert_eq!(debugger.run().unwrap(), Stop::Halted);
+    }
+
+    #[test]
+    fn step_formats_registers_and_output() {
+        let mut debugger = Debugger::new(super::super::parse(OUTPUT_EXAMPLE));
+        debugger.step().unwrap();
+        let out = debugger.step().unwrap().unwrap().to_string();
+        assert_eq!(
+            out,
+            " 2: out A    A=364              B=0                C=0 out 4"
+        );
+    }
+
+    #[test]
+    fn stops_at_instruction_pointer() {
+        let mut debugger = Debugger::new(super::super::parse(INPUT));
+        debugger.add_breakpoint(Breakpoint::InstructionPointer(2));
+
+        // Stops before every output, having output everything before it
+        let mut outputs = Vec::new();
+        while let Stop::Breakpoint(idx) = debugger.run().unwrap() {
+            assert_eq!(idx, 0);
+            outputs.push(debugger.output().len());
+        }
+        assert_eq!(outputs, (0..outputs.len()).collect::<Vec<_>>());
+        assert_eq!(debugger.output(), vec![5, 7, 3, 0]);
+    }
+
+    #[test]
+    fn stops_on_condition() {
+        let mut debugger = Debugger::new(super::super::parse(OUTPUT_EXAMPLE));
+        debugger.add_breakpoint(Breakpoint::InstructionPointer(100));
+        debugger.add_breakpoint(Breakpoint::Condition(Box::new(|r| r.a < 10)));
+
+        assert_eq!(debugger.run().unwrap(), Stop::Breakpoint(1));
+        assert_eq!(debugger.registers().a, 5);
+        assert_eq!(debugger.output(), vec![4, 6, 3, 5, 6, 3]);
+        assert_eq!(debugger.trace().len(), 19);
+
+        // The condition still holds, but run always makes progress
+        assert_eq!(debugger.run().unwrap(), Stop::Breakpoint(1));
+        assert_eq!(debugger.trace().len(), 20);
+        assert_eq!(debugger.output(), vec![4, 6, 3, 5, 6, 3, 5]);
+    }
+
+    #[test]
+    fn stops_on_invalid_instructions() {
+        // bxl 7 is fine, but adv 7 has a combo operand of 7
+        let mut computer = super::super::parse(INPUT);
+        computer.mem = vec![1, 7, 0, 7];
+        let mut debugger = Debugger::new(computer);
+        let err = debugger.run().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Couldn't decode the instruction at 2: Invalid combo operand 7"
+        );
+        // The instruction before it ran, and the computer stopped in front of the bad one
+        assert_eq!(debugger.trace().len(), 1);
+        assert_eq!(debugger.registers().b, 7);
+        assert_eq!(debugger.instruction_pointer(), 2);
+        assert!(debugger.step().is_err());
+
+        let mut computer = super::super::parse(INPUT);
+        computer.mem = vec![0];
+        let mut debugger = Debugger::new(computer);
+        let err = debugger.run().unwrap_err();
+        assert_eq!(err.to_string(), "The instruction at 0 has no operand");
+        assert!(debugger.trace().is_empty());
+    }
+}
@@ -1,7 +1,7 @@
 #[inline]
 pub fn part1(input: &str) -> String {
     let mut computer = parse(input);
-    let output = computer.execute();
+    let output = computer.execute().unwrap();
     output
         .iter()
         .map(u64::to_string)
@@ -12,42 +12,15 @@ pub fn part1(input: &str) -> String {
 #[inline]
 pub fn part2(input: &str) -> u64 {
     let computer = parse(input);
-    let desired_output = computer.mem.clone();
-    let d_len = desired_output.len();
-
-    let mut override_a = 1;
-    loop {
-        // Check if this is the correct value
-        let mut cloned_computer = computer.clone();
-        cloned_computer.A = override_a;
-        let output = cloned_computer.execute();
-        if output == desired_output {
-            return override_a;
-        }
-
-        // The output length is proportional to size of the override
-        // Multiplying by 10 allows us to quickly converge on the real answer
-        if output.len() < d_len {
-            override_a *= 10;
-            continue;
-        }
-        // If the last digit doesn't match, increment by a lot - `multiple``
-        // If the penultimate digit doesn't match, increment by a bit less - `multiple/10` and so on.
-        let mut multiple = 10u64.pow(override_a.ilog10() - 3);
-        for (i, val) in output.iter().enumerate().rev() {
-            if *val != desired_output[i] {
-                override_a += multiple;
-                break;
-            }
-            multiple /= 8;
-        }
-        override_a += 1;
-    }
+    computer.find_quine().unwrap()
 }
 
+pub mod debugger;
+pub mod symbolic;
+
 #[allow(non_snake_case)]
 #[derive(Clone)]
-struct Computer {
+pub struct Computer {
     A: u64,
     B: u64,
     C: u64,
@@ -55,90 +28,226 @@ struct Computer {
     instruction_pointer: usize,
 }
 
+/// Operand of the instructions that take a combo operand.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Combo {
+    Literal(u64),
+    A,
+    B,
+    C,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    /// A = A >> combo
+    Adv(Combo),
+    /// B = B ^ literal
+    Bxl(u64),
+    /// B = combo % 8
+    Bst(Combo),
+    /// Jump to literal if A != 0
+    Jnz(usize),
+    /// B = B ^ C
+    Bxc,
+    /// Output combo % 8
+    Out(Combo),
+    /// B = A >> combo
+    Bdv(Combo),
+    /// C = A >> combo
+    Cdv(Combo),
+}
+
 impl Computer {
-    fn execute(&mut self) -> Vec<u64> {
+    fn execute(&mut self) -> anyhow::Result<Vec<u64>> {
         let mut result = Vec::with_capacity(20);
         while self.is_instruction_pointer_valid() {
-            if let Some(output) = self.execute_one() {
+            let instruction = self.current_instruction()?;
+            if let Some(output) = self.execute_one(instruction) {
                 result.push(output);
             }
         }
-        result
-    }
-
-    fn execute_one(&mut self) -> Option<u64> {
-        let opcode = self.mem[self.instruction_pointer];
-        let operand = self.mem[self.instruction_pointer + 1];
-        match opcode {
-            // adv
-            0 => {
-                let combo_operand = self.operand_val(operand);
-                self.A /= 2u64.pow(combo_operand as u32);
-                self.instruction_pointer += 2;
-            }
-            // bxl
-            1 => {
-                self.B ^= operand;
-                self.instruction_pointer += 2;
-            }
-            // bst
-            2 => {
-                let combo_operand = self.operand_val(operand);
-                self.B = combo_operand % 8;
-                self.instruction_pointer += 2;
-            }
-            // jnz
-            3 => {
+        Ok(result)
+    }
+
+    // Runs the instruction at the instruction pointer, which has already been decoded.
+    fn execute_one(&mut self, instruction: Instruction) -> Option<u64> {
+        self.instruction_pointer += 2;
+        match instruction {
+            Instruction::Adv(combo) => self.A = shift_right(self.A, self.combo_val(combo)),
+            Instruction::Bxl(literal) => self.B ^= literal,
+            Instruction::Bst(combo) => self.B = self.combo_val(combo) % 8,
+            Instruction::Jnz(target) => {
                 if self.A != 0 {
-                    self.instruction_pointer = operand as usize;
-                } else {
-                    self.instruction_pointer += 2;
+                    self.instruction_pointer = target;
                 }
             }
-            // bxc
-            4 => {
-                self.B ^= self.C;
-                self.instruction_pointer += 2;
-            }
-            // out
-            5 => {
-                let combo_operand = self.operand_val(operand);
-                self.instruction_pointer += 2;
-                return Some(combo_operand % 8);
-            }
-            // bdv
-            6 => {
-                let combo_operand = self.operand_val(operand);
-                self.B = self.A / 2u64.pow(combo_operand as u32);
-                self.instruction_pointer += 2;
-            }
-            // cdv
-            7 => {
-                let combo_operand = self.operand_val(operand);
-                self.C = self.A / 2u64.pow(combo_operand as u32);
-                self.instruction_pointer += 2;
-            }
-            _ => unreachable!("Invalid opcode"),
+            Instruction::Bxc => self.B ^= self.C,
+            Instruction::Out(combo) => return Some(self.combo_val(combo) % 8),
+            Instruction::Bdv(combo) => self.B = shift_right(self.A, self.combo_val(combo)),
+            Instruction::Cdv(combo) => self.C = shift_right(self.A, self.combo_val(combo)),
         };
         None
     }
 
+    fn current_instruction(&self) -> anyhow::Result<Instruction> {
+        let ip = self.instruction_pointer;
+        let Some(&[opcode, operand]) = self.mem.get(ip..ip + 2) else {
+            anyhow::bail!("The instruction at {ip} has no operand");
+        };
+        Instruction::decode(opcode, operand)
+            .map_err(|e| anyhow::anyhow!("Couldn't decode the instruction at {ip}: {e}"))
+    }
+
+    /// Finds the smallest value of A that makes the program output itself.
+    ///
+    /// The program has to be a single loop that shifts A right by 3 and outputs once per iteration,
+    /// with B and C computed from A. Then each output depends only on the octal digits of A
+    /// from that iteration onwards, so A can be built up one octal digit at a time, starting with
+    /// the digit that produces the last output. Each digit is checked against the closed form
+    /// of the output from `symbolic_loop`, instead of running the program.
+    pub fn find_quine(&self) -> anyhow::Result<u64> {
+        let symbolic = self.symbolic_loop()?;
+        let a = self
+            .search_quine(&symbolic.output, 0, self.mem.len())
+            .ok_or_else(|| anyhow::anyhow!("No value of A makes this program output itself"))?;
+
+        let mut computer = self.clone();
+        computer.A = a;
+        anyhow::ensure!(
+            computer.execute()? == self.mem,
+            "Found {a}, but it isn't a quine"
+        );
+        Ok(a)
+    }
+
+    fn check_quine_shape(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.mem.len().is_multiple_of(2),
+            "The program has an opcode without an operand"
+        );
+        let instructions = self
+            .mem
+            .chunks_exact(2)
+            .map(|chunk| Instruction::decode(chunk[0], chunk[1]))
+            .collect::<anyhow::Result<Vec<Instruction>>>()?;
+        let count = |predicate: fn(&Instruction) -> bool| {
+            instructions.iter().filter(|i| predicate(i)).count()
+        };
+
+        anyhow::ensure!(
+            instructions.last() == Some(&Instruction::Jnz(0)),
+            "The program has to end by jumping back to the start"
+        );
+        anyhow::ensure!(
+            count(|i| matches!(i, Instruction::Jnz(_))) == 1,
+            "The program can only jump once, at the end"
+        );
+        anyhow::ensure!(
+            count(|i| matches!(i, Instruction::Adv(_))) == 1
+                && count(|i| *i == Instruction::Adv(Combo::Literal(3))) == 1,
+            "A has to be shifted right by 3 exactly once per loop"
+        );
+        anyhow::ensure!(
+            count(|i| matches!(i, Instruction::Out(_))) == 1,
+            "The program has to output exactly once per loop"
+        );
+
+        // If B or C is read before it's written, it carries state from the previous iteration.
+        let mut written = Vec::with_capacity(2);
+        for instruction in &instructions {
+            let (reads, write) = instruction.reads_and_writes();
+            if let Some(register) = reads
+                .iter()
+                .find(|r| matches!(r, Combo::B | Combo::C) && !written.contains(*r))
+            {
+                anyhow::bail!("{instruction:?} reads {register:?} before the loop sets it");
+            }
+            written.extend(write);
+        }
+        Ok(())
+    }
+
+    // `a` makes the program produce the last `mem.len() - remaining` outputs.
+    // Trying the next octal digit from smallest to largest means the first full match is the smallest.
+    fn search_quine(&self, output: &symbolic::Expr, a: u64, remaining: usize) -> Option<u64> {
+        if remaining == 0 {
+            return Some(a);
+        }
+        let target = self.mem[remaining - 1];
+        (0..8)
+            .filter_map(|digit| Some(a.checked_mul(8)? + digit))
+            .filter(|candidate| *candidate != 0 && output.eval(*candidate) == target)
+            .find_map(|candidate| self.search_quine(output, candidate, remaining - 1))
+    }
+
     fn is_instruction_pointer_valid(&self) -> bool {
         self.instruction_pointer < self.mem.len()
     }
 
-    fn operand_val(&self, operand: u64) -> u64 {
-        match operand {
-            n @ 0..=3 => n,
-            4 => self.A,
-            5 => self.B,
-            6 => self.C,
-            _ => unreachable!("Invalid operand"),
+    fn combo_val(&self, combo: Combo) -> u64 {
+        match combo {
+            Combo::Literal(n) => n,
+            Combo::A => self.A,
+            Combo::B => self.B,
+            Combo::C => self.C,
         }
     }
 }
 
-fn parse(input: &str) -> Computer {
+impl Instruction {
+    pub fn decode(opcode: u64, operand: u64) -> anyhow::Result<Instruction> {
+        Ok(match opcode {
+            0 => Instruction::Adv(Combo::decode(operand)?),
+            1 => Instruction::Bxl(operand),
+            2 => Instruction::Bst(Combo::decode(operand)?),
+            3 => Instruction::Jnz(operand as usize),
+            4 => Instruction::Bxc,
+            5 => Instruction::Out(Combo::decode(operand)?),
+            6 => Instruction::Bdv(Combo::decode(operand)?),
+            7 => Instruction::Cdv(Combo::decode(operand)?),
+            _ => anyhow::bail!("Invalid opcode {opcode}"),
+        })
+    }
+}
+
+impl Instruction {
+    // The registers an instruction reads and the register it writes, as combo operands.
+    fn reads_and_writes(&self) -> (Vec<Combo>, Option<Combo>) {
+        match *self {
+            Instruction::Adv(combo) => (vec![Combo::A, combo], Some(Combo::A)),
+            Instruction::Bxl(_) => (vec![Combo::B], Some(Combo::B)),
+            Instruction::Bst(combo) => (vec![combo], Some(Combo::B)),
+            Instruction::Jnz(_) => (vec![Combo::A], None),
+            Instruction::Bxc => (vec![Combo::B, Combo::C], Some(Combo::B)),
+            Instruction::Out(combo) => (vec![combo], None),
+            Instruction::Bdv(combo) => (vec![Combo::A, combo], Some(Combo::B)),
+            Instruction::Cdv(combo) => (vec![Combo::A, combo], Some(Combo::C)),
+        }
+    }
+}
+
+impl Combo {
+    fn decode(operand: u64) -> anyhow::Result<Combo> {
+        Ok(match operand {
+            n @ 0..=3 => Combo::Literal(n),
+            4 => Combo::A,
+            5 => Combo::B,
+            6 => Combo::C,
+            _ => anyhow::bail!("Invalid combo operand {operand}"),
+        })
+    }
+}
+
+// The division instructions divide by 2^combo, which is a right shift.
+// Shifting a u64 by 64 or more would overflow, but the result of the division is 0.
+fn shift_right(value: u64, shift: u64) -> u64 {
+    if shift >= u64::BITS as u64 {
+        return 0;
+    }
+    value >> shift
+}
+
+pub fn parse(input: &str) -> Computer {
     let (registers, memory) = input.split_once("\n\n").expect("input is valid");
     #[allow(non_snake_case)]
     let (A, B, C) = scan_fmt::scan_fmt!(
@@ -173,6 +282,89 @@ fn parse(input: &str) -> Computer {
     }
 }
 
+#[cfg(test)]
+mod quine_tests {
+    use super::*;
+
+    fn program(mem: &[u64]) -> Computer {
+        Computer {
+            A: 0,
+            B: 0,
+            C: 0,
+            mem: mem.to_vec(),
+            instruction_pointer: 0,
+        }
+    }
+
+    fn rejection(mem: &[u64]) -> String {
+        program(mem).find_quine().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn finds_example_quine() {
+        let computer = parse(include_str!("test-input.txt"));
+        assert_eq!(computer.find_quine().unwrap(), 117440);
+    }
+
+    #[test]
+    fn rejects_programs_that_dont_loop() {
+        assert_eq!(
+            rejection(&[0, 3, 5, 4, 3]),
+            "The program has an opcode without an operand"
+        );
+        assert_eq!(
+            rejection(&[0, 3, 5, 4]),
+            "The program has to end by jumping back to the start"
+        );
+        assert_eq!(
+            rejection(&[0, 3, 5, 4, 3, 2]),
+            "The program has to end by jumping back to the start"
+        );
+    }
+
+    #[test]
+    fn rejects_extra_jumps() {
+        assert_eq!(
+            rejection(&[0, 3, 3, 0, 5, 4, 3, 0]),
+            "The program can only jump once, at the end"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_shift_by_3() {
+        let message = "A has to be shifted right by 3 exactly once per loop";
+        assert_eq!(rejection(&[5, 4, 3, 0]), message);
+        assert_eq!(rejection(&[0, 1, 5, 4, 3, 0]), message);
+        assert_eq!(rejection(&[0, 3, 0, 3, 5, 4, 3, 0]), message);
+        // Shifting by a register that happens to hold 3 isn't enough
+        assert_eq!(rejection(&[1, 3, 0, 5, 5, 4, 3, 0]), message);
+    }
+
+    #[test]
+    fn rejects_more_than_one_output() {
+        let message = "The program has to output exactly once per loop";
+        assert_eq!(rejection(&[0, 3, 5, 4, 5, 4, 3, 0]), message);
+        assert_eq!(rejection(&[0, 3, 3, 0]), message);
+    }
+
+    #[test]
+    fn rejects_state_carried_between_loops() {
+        assert_eq!(
+            rejection(&[0, 3, 5, 5, 3, 0]),
+            "Out(B) reads B before the loop sets it"
+        );
+        assert_eq!(
+            rejection(&[2, 4, 4, 0, 0, 3, 5, 5, 3, 0]),
+            "Bxc reads C before the loop sets it"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_operand() {
+        assert_eq!(rejection(&[0, 7, 5, 4, 3, 0]), "Invalid combo operand 7");
+    }
+}
+
 common::aoctest!(
     "5,7,3,0".to_string(),
     "7,1,2,3,2,6,7,2,5".to_string(),
@@ -0,0 +1,217 @@
+use std::fmt;
+
+use super::{Combo, Computer, Instruction, shift_right};
+
+/// A register's value as an expression over the value A had at the start of the loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    A,
+    Const(u64),
+    Shr(Box<Expr>, Box<Expr>),
+    Xor(Vec<Expr>),
+    Mod(Box<Expr>, u64),
+}
+
+/// One iteration of a program's loop, run with A unknown.
+#[derive(Debug, Clone)]
+pub struct SymbolicLoop {
+    pub output: Expr,
+    pub next_a: Expr,
+}
+
+impl Computer {
+    /// Runs one iteration of the loop symbolically. The program has to have the shape
+    /// `find_quine` needs, so that every register is a function of A alone.
+    pub fn symbolic_loop(&self) -> anyhow::Result<SymbolicLoop> {
+        self.check_quine_shape()?;
+
+        let mut a = Expr::A;
+        let mut b = Expr::Const(self.B);
+        let mut c = Expr::Const(self.C);
+        let mut output = None;
+        for chunk in self.mem.chunks_exact(2) {
+            let combo = |combo: Combo, a: &Expr, b: &Expr, c: &Expr| match combo {
+                Combo::Literal(n) => Expr::Const(n),
+                Combo::A => a.clone(),
+                Combo::B => b.clone(),
+                Combo::C => c.clone(),
+            };
+            match Instruction::decode(chunk[0], chunk[1])? {
+                Instruction::Adv(operand) => a = Expr::shr(a.clone(), combo(operand, &a, &b, &c)),
+                Instruction::Bxl(literal) => b = Expr::xor(b, Expr::Const(literal)),
+                Instruction::Bst(operand) => b = Expr::modulo(combo(operand, &a, &b, &c), 8),
+                // The shape check guarantees this is the jump back to the start
+                Instruction::Jnz(_) => {}
+                Instruction::Bxc => b = Expr::xor(b, c.clone()),
+                Instruction::Out(operand) => {
+                    output = Some(Expr::modulo(combo(operand, &a, &b, &c), 8))
+                }
+                Instruction::Bdv(operand) => b = Expr::shr(a.clone(), combo(operand, &a, &b, &c)),
+                Instruction::Cdv(operand) => c = Expr::shr(a.clone(), combo(operand, &a, &b, &c)),
+            }
+        }
+
+        Ok(SymbolicLoop {
+            output: output.expect("the shape check guarantees one output"),
+            next_a: a,
+        })
+    }
+}
+
+impl Expr {
+    pub fn eval(&self, a: u64) -> u64 {
+        match self {
+            Expr::A => a,
+            Expr::Const(n) => *n,
+            Expr::Shr(value, shift) => shift_right(value.eval(a), shift.eval(a)),
+            Expr::Xor(operands) => operands.iter().fold(0, |acc, e| acc ^ e.eval(a)),
+            Expr::Mod(value, modulus) => value.eval(a) % modulus,
+        }
+    }
+
+    fn shr(value: Expr, shift: Expr) -> Expr {
+        match (value, shift) {
+            (value, Expr::Const(0)) => value,
+            (Expr::Const(value), Expr::Const(shift)) => Expr::Const(shift_right(value, shift)),
+            (value, shift) => Expr::Shr(Box::new(value), Box::new(shift)),
+        }
+    }
+
+    // Nested xors are flattened, and all the constants are folded into the first one.
+    fn xor(left: Expr, right: Expr) -> Expr {
+        let mut operands: Vec<Expr> = Vec::new();
+        let mut constant_idx = None;
+        for operand in [left, right].into_iter().flat_map(|e| match e {
+            Expr::Xor(operands) => operands,
+            e => vec![e],
+        }) {
+            match (operand, constant_idx) {
+                (Expr::Const(n), Some(idx)) => {
+                    if let Expr::Const(existing) = &mut operands[idx] {
+                        *existing ^= n;
+                    }
+                }
+                (operand, _) => {
+                    if matches!(operand, Expr::Const(_)) {
+                        constant_idx = Some(operands.len());
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+        operands.retain(|e| *e != Expr::Const(0));
+
+        match operands.len() {
+            0 => Expr::Const(0),
+            1 => operands.pop().unwrap(),
+            _ => Expr::Xor(operands),
+        }
+    }
+
+    fn modulo(value: Expr, modulus: u64) -> Expr {
+        match value {
+            Expr::Const(n) => Expr::Const(n % modulus),
+            Expr::Mod(inner, m) if m == modulus => Expr::Mod(inner, m),
+            value => Expr::Mod(Box::new(value), modulus),
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::A | Expr::Const(_) => write!(f, "{self}"),
+            _ => write!(f, "({self})"),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::A => write!(f, "A"),
+            Expr::Const(n) => write!(f, "{n}"),
+            Expr::Shr(value, shift) => {
+                value.fmt_operand(f)?;
+                write!(f, ">>")?;
+                shift.fmt_operand(f)
+            }
+            Expr::Xor(operands) => {
+                for (idx, operand) in operands.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, "^")?;
+                    }
+                    operand.fmt_operand(f)?;
+                }
+                Ok(())
+            }
+            Expr::Mod(value, modulus) => {
+                value.fmt_operand(f)?;
+                write!(f, "%{modulus}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for SymbolicLoop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "out = {}", self.output)?;
+        write!(f, "A = {}", self.next_a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prints_example_loop() {
+        let computer = super::super::parse(include_str!("test-input.txt"));
+        let symbolic = computer.symbolic_loop().unwrap();
+        assert_eq!(symbolic.to_string(), "out = (A>>3)%8\nA = A>>3");
+    }
+
+    #[test]
+    fn prints_input_loop() {
+        let computer = super::super::parse(include_str!("input.txt"));
+        let symbolic = computer.symbolic_loop().unwrap();
+        assert_eq!(
+            symbolic.to_string(),
+            "out = ((A%8)^5^(A>>((A%8)^1)))%8\nA = A>>3"
+        );
+    }
+
+    #[test]
+    fn closed_form_matches_execution() {
+        for input in [include_str!("test-input.txt"), include_str!("input.txt")] {
+            let computer = super::super::parse(input);
+            let symbolic = computer.symbolic_loop().unwrap();
+            let mut state: u64 = 1;
+            for _ in 0..1000 {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let a = state >> 16;
+
+                let mut concrete = computer.clone();
+                concrete.A = a;
+                let first = concrete.execute().unwrap()[0];
+                assert_eq!(symbolic.output.eval(a), first, "A = {a}");
+                assert_eq!(symbolic.next_a.eval(a), a >> 3);
+            }
+        }
+    }
+
+    #[test]
+    fn folds_constants() {
+        assert_eq!(Expr::xor(Expr::Const(3), Expr::Const(5)), Expr::Const(6));
+        assert_eq!(Expr::xor(Expr::A, Expr::Const(0)), Expr::A);
+        assert_eq!(
+            Expr::xor(Expr::xor(Expr::Const(1), Expr::A), Expr::Const(4)).to_string(),
+            "5^A"
+        );
+        assert_eq!(Expr::shr(Expr::A, Expr::Const(0)), Expr::A);
+        assert_eq!(Expr::shr(Expr::Const(16), Expr::Const(2)), Expr::Const(4));
+        assert_eq!(Expr::shr(Expr::Const(1), Expr::Const(64)), Expr::Const(0));
+        assert_eq!(Expr::modulo(Expr::Const(13), 8), Expr::Const(5));
+        assert_eq!(Expr::modulo(Expr::modulo(Expr::A, 8), 8).to_string(), "A%8");
+    }
+}
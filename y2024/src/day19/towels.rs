@@ -0,0 +1,257 @@
+/// The available towels, stored in a trie so that every towel that fits at a position in a pattern
+/// is found in one walk, however long the towels are.
+#[derive(Debug, Clone)]
+pub struct Towels {
+    // Which column of `children` a byte uses, NONE for bytes that no towel has
+    columns: [u8; 256],
+    width: usize,
+    // `width` entries per node, 0 where there's no child. The root is node 0, so it's never a child.
+    children: Vec<u32>,
+    // Whether a towel ends at each node
+    complete: Vec<bool>,
+}
+
+const NONE: u8 = u8::MAX;
+
+impl Towels {
+    /// Panics if the towels use more than 255 distinct bytes.
+    pub fn new<'a>(towels: impl IntoIterator<Item = &'a str>) -> Towels {
+        let towels: Vec<&str> = towels.into_iter().collect();
+        let mut columns = [NONE; 256];
+        let mut width = 0;
+        for byte in towels.iter().flat_map(|towel| towel.bytes()) {
+            if columns[byte as usize] == NONE {
+                assert!(width < NONE as usize, "too many distinct stripes");
+                columns[byte as usize] = width as u8;
+                width += 1;
+            }
+        }
+
+        let mut trie = Towels {
+            columns,
+            width,
+            children: vec![0; width],
+            complete: vec![false],
+        };
+        for towel in towels {
+            let mut node = 0;
+            for byte in towel.bytes() {
+                let slot = node * width + columns[byte as usize] as usize;
+                if trie.children[slot] == 0 {
+                    trie.children[slot] = trie.complete.len() as u32;
+                    trie.complete.push(false);
+                    trie.children.extend(std::iter::repeat_n(0, width));
+                }
+                node = trie.children[slot] as usize;
+            }
+            trie.complete[node] = true;
+        }
+        trie
+    }
+
+    /// Number of ways to lay out towels, end to end, to make `pattern`.
+    /// Overflows past u64::MAX ways, which takes patterns much longer than the puzzle's.
+    pub fn count(&self, pattern: &str) -> u64 {
+        self.ways(pattern)[0]
+    }
+
+    pub fn is_possible(&self, pattern: &str) -> bool {
+        self.count(pattern) > 0
+    }
+
+    /// Every way to make `pattern`, each as the towels in order. Only ways that lead to a complete
+    /// pattern are explored, so each step of the iterator is cheap even when there are trillions.
+    pub fn decompositions<'p>(&self, pattern: &'p str) -> Decompositions<'p> {
+        let ways = self.ways(pattern);
+        let ends = (0..pattern.len())
+            .map(|start| {
+                self.ends(pattern, start)
+                    .filter(|end| ways[*end] > 0)
+                    .collect()
+            })
+            .collect();
+        Decompositions {
+            pattern,
+            ends,
+            stack: Vec::new(),
+            state: if ways[0] > 0 {
+                State::Start
+            } else {
+                State::Done
+            },
+        }
+    }
+
+    /// The way to make `pattern` at position `index` in the order `decompositions` returns them,
+    /// without going through the ones before it. A uniformly random index below `count(pattern)`
+    /// picks a uniformly random way. Returns None if `index` is out of range.
+    pub fn decomposition<'p>(&self, pattern: &'p str, mut index: u64) -> Option<Vec<&'p str>> {
+        let ways = self.ways(pattern);
+        if index >= ways[0] {
+            return None;
+        }
+        let mut towels = Vec::new();
+        let mut start = 0;
+        while start < pattern.len() {
+            // Skip past the ends whose ways all come before index
+            let end = self.ends(pattern, start).find(|end| {
+                if index < ways[*end] {
+                    return true;
+                }
+                index -= ways[*end];
+                false
+            })?;
+            towels.push(&pattern[start..end]);
+            start = end;
+        }
+        Some(towels)
+    }
+
+    // ways[i] is the number of ways to make pattern[i..], filled in from the end.
+    fn ways(&self, pattern: &str) -> Vec<u64> {
+        let mut ways = vec![0; pattern.len() + 1];
+        ways[pattern.len()] = 1;
+        for start in (0..pattern.len()).rev() {
+            ways[start] = self.ends(pattern, start).map(|end| ways[end]).sum();
+        }
+        ways
+    }
+
+    // Where each towel that matches pattern[start..] ends, shortest first.
+    fn ends<'s>(&'s self, pattern: &'s str, start: usize) -> impl Iterator<Item = usize> + 's {
+        let bytes = &pattern.as_bytes()[start..];
+        let mut node = 0;
+        bytes
+            .iter()
+            .map_while(move |byte| {
+                let column = self.columns[*byte as usize];
+                if column == NONE {
+                    return None;
+                }
+                node = self.children[node * self.width + column as usize] as usize;
+                (node != 0).then_some(node)
+            })
+            .enumerate()
+            .filter(|(_, node)| self.complete[*node])
+            .map(move |(len, _)| start + len + 1)
+    }
+}
+
+/// Iterator over the ways to make a pattern, from `Towels::decompositions`.
+pub struct Decompositions<'p> {
+    pattern: &'p str,
+    // For each position, where the towels that fit there end, keeping only the ends the rest of the
+    // pattern can be made from
+    ends: Vec<Vec<usize>>,
+    // The position of each towel in the current way, and which of the ends there it uses
+    stack: Vec<(usize, usize)>,
+    state: State,
+}
+
+enum State {
+    Start,
+    Running,
+    Done,
+}
+
+impl Decompositions<'_> {
+    // Takes the first end everywhere from `start` to the end of the pattern.
+    fn descend(&mut self, mut start: usize) {
+        while start < self.pattern.len() {
+            self.stack.push((start, 0));
+            start = self.ends[start][0];
+        }
+    }
+}
+
+impl<'p> Iterator for Decompositions<'p> {
+    type Item = Vec<&'p str>;
+
+    fn next(&mut self) -> Option<Vec<&'p str>> {
+        match self.state {
+            State::Done => return None,
+            State::Start => {
+                self.state = State::Running;
+                self.descend(0);
+            }
+            State::Running => loop {
+                let Some((start, choice)) = self.stack.pop() else {
+                    self.state = State::Done;
+                    return None;
+                };
+                if let Some(end) = self.ends[start].get(choice + 1) {
+                    self.stack.push((start, choice + 1));
+                    self.descend(*end);
+                    break;
+                }
+            },
+        }
+        Some(
+            self.stack
+                .iter()
+                .map(|(start, choice)| &self.pattern[*start..self.ends[*start][*choice]])
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_INPUT: &str = include_str!("test-input.txt");
+
+    fn example() -> (Towels, Vec<&'static str>) {
+        let (towels, patterns) = TEST_INPUT.split_once("\n\n").unwrap();
+        (Towels::new(towels.split(", ")), patterns.lines().collect())
+    }
+
+    #[test]
+    fn counts_the_example() {
+        let (towels, patterns) = example();
+        let counts: Vec<u64> = patterns.iter().map(|p| towels.count(p)).collect();
+        assert_eq!(counts, vec![2, 1, 4, 6, 0, 1, 2, 0]);
+        assert!(towels.is_possible("brwrr"));
+        assert!(!towels.is_possible("ubwu"));
+        assert_eq!(towels.count(""), 1);
+    }
+
+    #[test]
+    fn lists_every_decomposition() {
+        let (towels, patterns) = example();
+        assert_eq!(
+            towels.decompositions("brwrr").collect::<Vec<_>>(),
+            vec![vec!["b", "r", "wr", "r"], vec!["br", "wr", "r"]]
+        );
+        for pattern in patterns {
+            let all: Vec<_> = towels.decompositions(pattern).collect();
+            assert_eq!(all.len() as u64, towels.count(pattern), "{pattern}");
+            for (index, towels_used) in all.iter().enumerate() {
+                assert_eq!(towels_used.concat(), pattern);
+                assert_eq!(
+                    towels.decomposition(pattern, index as u64).as_ref(),
+                    Some(towels_used),
+                    "{pattern} {index}"
+                );
+            }
+            assert_eq!(towels.decomposition(pattern, all.len() as u64), None);
+            assert_eq!(towels.decomposition(pattern, u64::MAX), None);
+        }
+    }
+
+    #[test]
+    fn handles_long_towels() {
+        let long = "a".repeat(12);
+        let towels = Towels::new(["a", long.as_str()]);
+        let pattern = "a".repeat(24);
+        // Up to two long towels, with single stripes around them
+        assert_eq!(towels.count(&pattern), 1 + 13 + 1);
+        assert_eq!(towels.decompositions(&pattern).count(), 15);
+        assert_eq!(towels.decomposition(&pattern, 0), Some(vec!["a"; 24]));
+        assert_eq!(
+            towels.decomposition(&pattern, 14),
+            Some(vec![long.as_str(), long.as_str()])
+        );
+        assert_eq!(towels.decomposition(&pattern, 15), None);
+    }
+}
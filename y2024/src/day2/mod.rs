@@ -1,60 +1,116 @@
+use std::ops::RangeInclusive;
+
 use rayon::prelude::*;
 use rayon::str::ParallelString;
 
 #[inline]
 pub fn part1(input: &str) -> usize {
-    parse(input).filter(|line| is_monotonic(line, None)).count()
+    parse(input).filter(|line| PART_1.is_safe(line)).count()
 }
 
 #[inline]
 pub fn part2(input: &str) -> usize {
-    parse(input).filter(|line| is_monotonic_safe(line)).count()
+    parse(input).filter(|line| PART_2.is_safe(line)).count()
 }
 
-fn is_monotonic_safe(numbers: &[u32]) -> bool {
-    if is_monotonic(numbers, None) {
-        return true;
-    }
+/// When a report counts as safe: its levels all increase or all decrease, by an amount in
+/// `steps`, once at most `tolerance` of them are removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Safety {
+    pub steps: RangeInclusive<u32>,
+    pub tolerance: usize,
+}
 
-    for i in 0..numbers.len() {
-        if is_monotonic(numbers, Some(i)) {
-            return true;
-        }
-    }
+pub const PART_1: Safety = Safety {
+    steps: 1..=3,
+    tolerance: 0,
+};
 
-    false
-}
+pub const PART_2: Safety = Safety {
+    steps: 1..=3,
+    tolerance: 1,
+};
+
+// Reports this long or shorter are checked without allocating. The puzzle's have at most 8 levels.
+const SHORT_REPORT: usize = 16;
 
-fn is_monotonic(numbers: &[u32], skip_index: Option<usize>) -> bool {
-    if numbers.is_empty() || numbers.len() == 1 {
-        return false;
+impl Safety {
+    pub fn is_safe(&self, levels: &[u32]) -> bool {
+        let mut buffer = [0; SHORT_REPORT];
+        let mut long;
+        let removed = if levels.len() <= SHORT_REPORT {
+            &mut buffer[..levels.len()]
+        } else {
+            long = vec![0; levels.len()];
+            &mut long[..]
+        };
+        levels.len() < 2
+            || [true, false]
+                .into_iter()
+                .any(|increasing| self.last_kept(levels, increasing, removed, None).is_some())
     }
-    let (first, second) = match skip_index {
-        Some(0) => (1, 2),
-        Some(1) => (0, 2),
-        _ => (0, 1),
-    };
-
-    let mut current = numbers[first];
-    let trend_increasing = numbers[first] > numbers[second];
-    let skip_index = match skip_index {
-        Some(s) => s,
-        None => numbers.len() + 1,
-    };
-    for (i, &n) in numbers.iter().enumerate().skip(second) {
-        if i == skip_index {
-            continue;
-        }
-        if current.abs_diff(n) < 1 || current.abs_diff(n) > 3 {
-            return false;
-        }
-        if (trend_increasing && current < n) || (!trend_increasing && current > n) {
-            return false;
+
+    /// The fewest indices to remove to make the report safe, in increasing order, or None if that
+    /// takes more than `tolerance`. A report with one level or none is always safe.
+    /// Takes O(n * tolerance) time, so it's linear for a fixed tolerance.
+    pub fn removals(&self, levels: &[u32]) -> Option<Vec<usize>> {
+        if levels.len() < 2 {
+            return Some(Vec::new());
         }
-        current = n;
+        let mut removed = vec![0; levels.len()];
+        let mut previous = vec![None; levels.len()];
+        [true, false]
+            .into_iter()
+            .filter_map(|increasing| {
+                let mut level =
+                    self.last_kept(levels, increasing, &mut removed, Some(&mut previous));
+                let mut kept = vec![false; levels.len()];
+                while let Some(i) = level {
+                    kept[i] = true;
+                    level = previous[i];
+                }
+                kept.contains(&true)
+                    .then(|| (0..levels.len()).filter(|i| !kept[*i]).collect::<Vec<_>>())
+            })
+            .min_by_key(Vec::len)
     }
 
-    true
+    // removed[i] is the fewest levels removed from levels[..=i] with level i kept. Only the last
+    // tolerance + 1 levels before i can be the previous one kept, which goes in previous[i].
+    // Returns the last level kept in the best way, if it removes no more than the tolerance.
+    fn last_kept(
+        &self,
+        levels: &[u32],
+        increasing: bool,
+        removed: &mut [usize],
+        mut previous: Option<&mut Vec<Option<usize>>>,
+    ) -> Option<usize> {
+        let fits = |from: u32, to: u32| {
+            (if increasing { to > from } else { to < from } || from == to)
+                && self.steps.contains(&from.abs_diff(to))
+        };
+        for i in 0..levels.len() {
+            // Removing everything before i
+            removed[i] = i;
+            let mut best = None;
+            for j in i.saturating_sub(self.tolerance + 1)..i {
+                let total = removed[j] + (i - j - 1);
+                if total < removed[i] && fits(levels[j], levels[i]) {
+                    removed[i] = total;
+                    best = Some(j);
+                }
+            }
+            if let Some(previous) = previous.as_mut() {
+                previous[i] = best;
+            }
+        }
+        // Removing everything after the last level kept
+        (0..levels.len())
+            .map(|i| (removed[i] + (levels.len() - 1 - i), i))
+            .min()
+            .filter(|(total, _)| *total <= self.tolerance)
+            .map(|(_, i)| i)
+    }
 }
 
 pub fn parse(input: &str) -> impl rayon::prelude::ParallelIterator<Item = Vec<u32>> + use<'_> {
@@ -65,4 +121,128 @@ pub fn parse(input: &str) -> impl rayon::prelude::ParallelIterator<Item = Vec<u3
     })
 }
 
+#[cfg(test)]
+mod safety_tests {
+    use super::*;
+
+    fn fits(safety: &Safety, levels: &[u32], increasing: bool) -> bool {
+        levels.windows(2).all(|pair| {
+            let (from, to) = (pair[0], pair[1]);
+            (if increasing { to > from } else { to < from } || from == to)
+                && safety.steps.contains(&from.abs_diff(to))
+        })
+    }
+
+    // The fewest levels to remove, trying every subset of them.
+    fn brute_force(safety: &Safety, levels: &[u32]) -> usize {
+        (0u32..1 << levels.len())
+            .filter(|mask| {
+                let kept: Vec<u32> = (0..levels.len())
+                    .filter(|i| mask & (1 << i) != 0)
+                    .map(|i| levels[i])
+                    .collect();
+                fits(safety, &kept, true) || fits(safety, &kept, false)
+            })
+            .map(|mask| levels.len() - mask.count_ones() as usize)
+            .min()
+            .expect("keeping one level or none is always safe")
+    }
+
+    fn check(safety: &Safety, levels: &[u32]) {
+        let fewest = brute_force(safety, levels);
+        let safe = fewest <= safety.tolerance;
+        assert_eq!(safety.is_safe(levels), safe, "{safety:?} {levels:?}");
+
+        let removals = safety.removals(levels);
+        assert_eq!(removals.is_some(), safe, "{safety:?} {levels:?}");
+        if let Some(removals) = removals {
+            assert_eq!(removals.len(), fewest, "{safety:?} {levels:?}");
+            assert!(removals.is_sorted());
+            let kept: Vec<u32> = (0..levels.len())
+                .filter(|i| !removals.contains(i))
+                .map(|i| levels[i])
+                .collect();
+            assert!(fits(safety, &kept, true) || fits(safety, &kept, false));
+        }
+    }
+
+    fn random_reports(count: usize, max_len: u64, max_level: u64) -> Vec<Vec<u32>> {
+        let mut state: u64 = 2;
+        let mut next = |n: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % n
+        };
+        (0..count)
+            .map(|_| {
+                let len = next(max_len + 1);
+                (0..len).map(|_| next(max_level + 1) as u32).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn example() {
+        let reports = [
+            (vec![7, 6, 4, 2, 1], Some(0)),
+            (vec![1, 2, 7, 8, 9], None),
+            (vec![9, 7, 6, 2, 1], None),
+            (vec![1, 3, 2, 4, 5], Some(1)),
+            (vec![8, 6, 4, 4, 1], Some(1)),
+            (vec![1, 3, 6, 7, 9], Some(0)),
+        ];
+        for (levels, removals) in reports {
+            let found = PART_2.removals(&levels);
+            assert_eq!(found.as_ref().map(Vec::len), removals, "{levels:?}");
+            assert_eq!(PART_1.is_safe(&levels), removals == Some(0));
+        }
+        // Either of the 4s can go, and the second one is the one removed
+        assert_eq!(PART_2.removals(&[8, 6, 4, 4, 1]), Some(vec![3]));
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for tolerance in 0..=3 {
+            let safety = Safety {
+                steps: 1..=3,
+                tolerance,
+            };
+            for levels in random_reports(2000, 10, 12) {
+                check(&safety, &levels);
+            }
+        }
+    }
+
+    #[test]
+    fn custom_steps() {
+        for (steps, tolerance) in [(2..=4, 1), (0..=1, 2), (0..=0, 1), (5..=5, 0)] {
+            let safety = Safety { steps, tolerance };
+            for levels in random_reports(2000, 9, 15) {
+                check(&safety, &levels);
+            }
+        }
+
+        // Equal levels are fine when the steps allow 0
+        let flat = Safety {
+            steps: 0..=2,
+            tolerance: 0,
+        };
+        assert!(flat.is_safe(&[4, 4, 5, 7, 7]));
+        assert!(!flat.is_safe(&[4, 5, 4]));
+    }
+
+    #[test]
+    fn long_reports() {
+        // Longer than the buffer is_safe uses
+        let levels: Vec<u32> = (0..40).collect();
+        assert!(PART_1.is_safe(&levels));
+        let mut bumped = levels.clone();
+        bumped[30] = 100;
+        assert!(!PART_1.is_safe(&bumped));
+        assert!(PART_2.is_safe(&bumped));
+        assert_eq!(PART_2.removals(&bumped), Some(vec![30]));
+    }
+}
+
 common::aoctest!(2, 591, 4, 621);
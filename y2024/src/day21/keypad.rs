@@ -0,0 +1,260 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use ahash::AHashMap;
+
+/// A grid of keys, one string per row. Spaces are gaps a robot arm must never point at.
+#[derive(Debug, Clone)]
+pub struct Keypad {
+    positions: AHashMap<char, (i32, i32)>,
+    keys: AHashMap<(i32, i32), char>,
+}
+
+const DIRECTIONS: [(char, (i32, i32)); 4] =
+    [('^', (-1, 0)), ('v', (1, 0)), ('<', (0, -1)), ('>', (0, 1))];
+
+/// Every robot arm starts on this key, and pressing it pushes the key the arm is pointing at.
+pub const PRESS: char = 'A';
+
+impl Keypad {
+    pub fn new(rows: &[&str]) -> Keypad {
+        let mut positions = AHashMap::new();
+        let mut keys = AHashMap::new();
+        for (row, line) in rows.iter().enumerate() {
+            for (col, key) in line.chars().enumerate() {
+                if key != ' ' {
+                    positions.insert(key, (row as i32, col as i32));
+                    keys.insert((row as i32, col as i32), key);
+                }
+            }
+        }
+        Keypad { positions, keys }
+    }
+
+    pub fn numeric() -> Keypad {
+        Keypad::new(&["789", "456", "123", " 0A"])
+    }
+
+    pub fn directional() -> Keypad {
+        Keypad::new(&[" ^A", "<v>"])
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = char> + '_ {
+        self.positions.keys().copied()
+    }
+
+    /// The keys next to `key`, with the direction that moves the arm there.
+    fn neighbours(&self, key: char) -> impl Iterator<Item = (char, char)> + '_ {
+        let (row, col) = self.positions[&key];
+        DIRECTIONS.iter().filter_map(move |(direction, (dr, dc))| {
+            let neighbour = self.keys.get(&(row + dr, col + dc))?;
+            Some((*direction, *neighbour))
+        })
+    }
+}
+
+/// The cheapest way to move an arm between two keys of one keypad and press the second,
+/// counted in presses by the human at the far end of the chain.
+#[derive(Debug, Clone)]
+struct Layer {
+    costs: AHashMap<(char, char), u64>,
+    // What has to be typed on the keypad controlling this one, ending with a press.
+    moves: AHashMap<(char, char), String>,
+}
+
+/// A human types on a directional keypad, which drives a robot typing on the next keypad, and so on.
+/// Layer 0 is the keypad the human is typing on, and the last layer is the one the code is
+/// typed into.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    layers: Vec<Layer>,
+}
+
+impl Chain {
+    /// `robots` robots typing on directional keypads, and one more typing on `target`.
+    pub fn new(target: &Keypad, robots: usize) -> Chain {
+        let directional = Keypad::directional();
+        let human = Layer {
+            costs: directional
+                .keys()
+                .flat_map(|from| directional.keys().map(move |to| ((from, to), 1)))
+                .collect(),
+            moves: AHashMap::new(),
+        };
+        let mut layers = vec![human];
+        for _ in 0..robots {
+            let layer = Layer::new(&directional, layers.last().unwrap());
+            layers.push(layer);
+        }
+        let layer = Layer::new(target, layers.last().unwrap());
+        layers.push(layer);
+        Chain { layers }
+    }
+
+    /// Presses the human needs to make to type `code` on the target keypad.
+    /// Returns None if the code has a key the keypad doesn't.
+    pub fn cost(&self, code: &str) -> Option<u64> {
+        let target = self.layers.last().unwrap();
+        std::iter::once(PRESS)
+            .chain(code.chars())
+            .map_windows(|&[from, to]| target.costs.get(&(from, to)).copied())
+            .sum()
+    }
+
+    /// One of the cheapest ways to move from `from` to `to` and press it on keypad `layer`,
+    /// written as the keys pressed on the keypad one layer closer to the human.
+    pub fn moves(&self, layer: usize, from: char, to: char) -> Option<&str> {
+        let moves = &self.layers.get(layer)?.moves;
+        moves.get(&(from, to)).map(String::as_str)
+    }
+
+    /// Everything the human presses to type `code`. The length grows exponentially with the
+    /// number of robots, so this is only useful for short chains; use `cost` otherwise.
+    pub fn expand(&self, code: &str) -> Option<String> {
+        let mut sequence = code.to_string();
+        for layer in self.layers[1..].iter().rev() {
+            sequence = std::iter::once(PRESS)
+                .chain(sequence.chars())
+                .map_windows(|&[from, to]| layer.moves.get(&(from, to)).map(String::as_str))
+                .collect::<Option<String>>()?;
+        }
+        Some(sequence)
+    }
+}
+
+impl Layer {
+    // Dijkstra over (key this arm points at, key the controlling arm points at).
+    // Moving the arm one step costs whatever it takes to get the controlling arm to that direction
+    // and press it. The controlling arm always starts on PRESS, since it just pressed the last key.
+    fn new(keypad: &Keypad, controller: &Layer) -> Layer {
+        let mut costs = AHashMap::new();
+        let mut moves = AHashMap::new();
+        for from in keypad.keys() {
+            let mut distances = AHashMap::from([((from, PRESS), 0)]);
+            let mut parents: AHashMap<(char, char), (char, char)> = AHashMap::new();
+            let mut queue = BinaryHeap::from([Reverse((0, from, PRESS))]);
+            while let Some(Reverse((distance, key, pressed))) = queue.pop() {
+                if distance > distances[&(key, pressed)] {
+                    continue;
+                }
+                for (direction, next) in keypad.neighbours(key) {
+                    let next_distance = distance + controller.costs[&(pressed, direction)];
+                    let best = distances.entry((next, direction)).or_insert(u64::MAX);
+                    if next_distance < *best {
+                        *best = next_distance;
+                        parents.insert((next, direction), (key, pressed));
+                        queue.push(Reverse((next_distance, next, direction)));
+                    }
+                }
+            }
+
+            for to in keypad.keys() {
+                let Some((cost, end)) = distances
+                    .iter()
+                    .filter(|((key, _), _)| *key == to)
+                    .map(|(state, distance)| {
+                        (distance + controller.costs[&(state.1, PRESS)], *state)
+                    })
+                    .min()
+                else {
+                    continue;
+                };
+                let mut path = vec![PRESS];
+                let mut state = end;
+                while let Some(parent) = parents.get(&state) {
+                    path.push(state.1);
+                    state = *parent;
+                }
+                costs.insert((from, to), cost);
+                moves.insert((from, to), path.into_iter().rev().collect());
+            }
+        }
+        Layer { costs, moves }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Moves an arm starting on PRESS around `keypad`, returning the keys it pushes.
+    fn type_on(keypad: &Keypad, presses: &str) -> String {
+        let mut position = keypad.positions[&PRESS];
+        let mut typed = String::new();
+        for press in presses.chars() {
+            if press == PRESS {
+                typed.push(keypad.keys[&position]);
+                continue;
+            }
+            let (_, (dr, dc)) = DIRECTIONS.iter().find(|(d, _)| *d == press).unwrap();
+            position = (position.0 + dr, position.1 + dc);
+            assert!(keypad.keys.contains_key(&position), "the arm is over a gap");
+        }
+        typed
+    }
+
+    // Undoes `expand`, checking every robot along the way stays on its keypad.
+    fn type_through(chain_robots: usize, presses: &str) -> String {
+        let mut typed = presses.to_string();
+        for _ in 0..chain_robots {
+            typed = type_on(&Keypad::directional(), &typed);
+        }
+        type_on(&Keypad::numeric(), &typed)
+    }
+
+    #[test]
+    fn expands_example_code() {
+        for (robots, length) in [(0, 12), (1, 28), (2, 68)] {
+            let chain = Chain::new(&Keypad::numeric(), robots);
+            let presses = chain.expand("029A").unwrap();
+            assert_eq!(presses.len(), length, "{robots} robots");
+            assert_eq!(chain.cost("029A"), Some(length as u64));
+            assert_eq!(type_through(robots, &presses), "029A");
+        }
+    }
+
+    #[test]
+    fn costs_match_expansion() {
+        for robots in 0..4 {
+            let chain = Chain::new(&Keypad::numeric(), robots);
+            for code in include_str!("test-input.txt").lines() {
+                let presses = chain.expand(code).unwrap();
+                assert_eq!(chain.cost(code), Some(presses.len() as u64));
+                assert_eq!(type_through(robots, &presses), code);
+            }
+        }
+    }
+
+    #[test]
+    fn depth_zero_is_shortest_path() {
+        let chain = Chain::new(&Keypad::numeric(), 0);
+        // Distance between the keys plus a press, avoiding the gap
+        assert_eq!(chain.cost("A"), Some(1));
+        assert_eq!(chain.cost("7"), Some(6));
+        assert_eq!(chain.cost("0"), Some(2));
+        // Any order of the moves costs the same, as long as it doesn't cross the gap
+        let moves = chain.moves(1, '7', '0').unwrap();
+        assert_eq!(moves.len(), 5);
+        assert_eq!(type_on(&Keypad::numeric(), &format!("^^^<<A{moves}")), "70");
+        assert_eq!(chain.moves(2, '0', '7'), None);
+    }
+
+    #[test]
+    fn depth_one_avoids_zigzags() {
+        let chain = Chain::new(&Keypad::numeric(), 1);
+        // Pressing the same direction twice is free for the robot, so moves are grouped
+        let moves = chain.moves(2, 'A', '7').unwrap();
+        assert_eq!(moves, "^^^<<A");
+        assert_eq!(
+            chain.cost("7"),
+            Some(chain.expand("7").unwrap().len() as u64)
+        );
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let chain = Chain::new(&Keypad::numeric(), 2);
+        assert_eq!(chain.cost("12B"), None);
+        assert_eq!(chain.expand("12B"), None);
+    }
+}
@@ -0,0 +1,444 @@
+use common::graph::Interner;
+
+/// A set of vertex ids, one bit per vertex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(capacity: usize) -> BitSet {
+        BitSet {
+            words: vec![0; capacity.div_ceil(64)],
+        }
+    }
+
+    pub fn full(len: usize) -> BitSet {
+        let mut set = BitSet::new(len);
+        for vertex in 0..len {
+            set.insert(vertex);
+        }
+        set
+    }
+
+    pub fn insert(&mut self, vertex: usize) {
+        self.words[vertex / 64] |= 1 << (vertex % 64);
+    }
+
+    pub fn remove(&mut self, vertex: usize) {
+        self.words[vertex / 64] &= !(1 << (vertex % 64));
+    }
+
+    pub fn contains(&self, vertex: usize) -> bool {
+        self.words[vertex / 64] & (1 << (vertex % 64)) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    pub fn intersection(&self, other: &BitSet) -> BitSet {
+        BitSet {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a & b)
+                .collect(),
+        }
+    }
+
+    pub fn union(&self, other: &BitSet) -> BitSet {
+        BitSet {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a | b)
+                .collect(),
+        }
+    }
+
+    pub fn difference(&self, other: &BitSet) -> BitSet {
+        BitSet {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a & !b)
+                .collect(),
+        }
+    }
+
+    pub fn intersection_len(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(idx, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(idx * 64 + bit)
+            })
+        })
+    }
+}
+
+/// An undirected graph. Vertex names are interned to ids `0..len()`.
+pub struct Graph<'a> {
+    vertices: Interner<'a>,
+    adjacency: Vec<BitSet>,
+    order: Vec<usize>,
+}
+
+impl<'a> Graph<'a> {
+    pub fn from_edges(edges: impl IntoIterator<Item = (&'a str, &'a str)>) -> Graph<'a> {
+        let mut graph = Graph {
+            vertices: Interner::new(),
+            adjacency: Vec::new(),
+            order: Vec::new(),
+        };
+        let edges: Vec<_> = edges
+            .into_iter()
+            .map(|(from, to)| (graph.vertices.intern(from), graph.vertices.intern(to)))
+            .collect();
+        graph.adjacency = vec![BitSet::new(graph.len()); graph.len()];
+        for (from, to) in edges {
+            graph.adjacency[from].insert(to);
+            graph.adjacency[to].insert(from);
+        }
+        graph.order = degeneracy_order(&graph.adjacency);
+        graph
+    }
+
+    pub fn len(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn name(&self, vertex: usize) -> &'a str {
+        self.vertices.name(vertex)
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.vertices.id(name)
+    }
+
+    pub fn neighbours(&self, vertex: usize) -> &BitSet {
+        &self.adjacency[vertex]
+    }
+
+    pub fn vertices_where(&self, predicate: impl Fn(&str) -> bool) -> BitSet {
+        let mut set = BitSet::new(self.len());
+        for (vertex, name) in self.vertices.names().iter().enumerate() {
+            if predicate(name) {
+                set.insert(vertex);
+            }
+        }
+        set
+    }
+
+    /// Vertices in the order they'd be removed by repeatedly taking the one with the fewest
+    /// remaining neighbours. No vertex has more neighbours later in the order than the graph's
+    /// degeneracy, which keeps the candidate sets in the clique searches small.
+    pub fn degeneracy_order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Calls `found` with every maximal clique, using Bron–Kerbosch with pivoting.
+    /// The outer loop follows the degeneracy order, as described by Eppstein, Löffler and Strash.
+    pub fn for_each_maximal_clique(&self, mut found: impl FnMut(&[usize])) {
+        let mut later = BitSet::full(self.len());
+        let mut earlier = BitSet::new(self.len());
+        let mut clique = Vec::new();
+        for &vertex in &self.order {
+            later.remove(vertex);
+            let neighbours = &self.adjacency[vertex];
+            clique.push(vertex);
+            self.bron_kerbosch(
+                &mut clique,
+                neighbours.intersection(&later),
+                neighbours.intersection(&earlier),
+                &mut found,
+            );
+            clique.pop();
+            earlier.insert(vertex);
+        }
+    }
+
+    pub fn maximum_clique(&self) -> Vec<usize> {
+        let mut maximum = Vec::new();
+        self.for_each_maximal_clique(|clique| {
+            if clique.len() > maximum.len() {
+                maximum = clique.to_vec();
+            }
+        });
+        maximum
+    }
+
+    /// Number of cliques of exactly `k` vertices, all of them in `within`.
+    pub fn count_cliques(&self, k: usize, within: &BitSet) -> usize {
+        // Only following edges to vertices later in the order finds each clique exactly once
+        let mut later = within.clone();
+        let mut forward = vec![BitSet::new(self.len()); self.len()];
+        for &vertex in &self.order {
+            later.remove(vertex);
+            forward[vertex] = self.adjacency[vertex].intersection(&later);
+        }
+        count_cliques(&forward, within, k)
+    }
+
+    // `candidates` can extend the clique, `excluded` could too but those cliques were already found.
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<usize>,
+        mut candidates: BitSet,
+        mut excluded: BitSet,
+        found: &mut impl FnMut(&[usize]),
+    ) {
+        if candidates.is_empty() {
+            if excluded.is_empty() {
+                found(clique);
+            }
+            return;
+        }
+
+        // Any maximal clique contains the pivot or one of its non-neighbours
+        let pivot = candidates
+            .union(&excluded)
+            .iter()
+            .max_by_key(|vertex| candidates.intersection_len(&self.adjacency[*vertex]))
+            .expect("candidates isn't empty");
+        for vertex in candidates.difference(&self.adjacency[pivot]).iter() {
+            let neighbours = &self.adjacency[vertex];
+            clique.push(vertex);
+            self.bron_kerbosch(
+                clique,
+                candidates.intersection(neighbours),
+                excluded.intersection(neighbours),
+                found,
+            );
+            clique.pop();
+            candidates.remove(vertex);
+            excluded.insert(vertex);
+        }
+    }
+}
+
+// A bucket queue of vertices by remaining degree. Vertices are pushed again when their degree
+// drops, and stale entries are skipped when popped.
+fn degeneracy_order(adjacency: &[BitSet]) -> Vec<usize> {
+    let mut degrees: Vec<usize> = adjacency.iter().map(BitSet::len).collect();
+    let mut buckets = vec![Vec::new(); degrees.iter().max().map_or(0, |max| max + 1)];
+    for (vertex, degree) in degrees.iter().enumerate() {
+        buckets[*degree].push(vertex);
+    }
+    let mut removed = vec![false; adjacency.len()];
+    let mut order = Vec::with_capacity(adjacency.len());
+    let mut lowest = 0;
+    while order.len() < adjacency.len() {
+        let Some(vertex) = buckets[lowest].pop() else {
+            lowest += 1;
+            continue;
+        };
+        if removed[vertex] || degrees[vertex] != lowest {
+            continue;
+        }
+        removed[vertex] = true;
+        order.push(vertex);
+        for neighbour in adjacency[vertex].iter().filter(|n| !removed[*n]) {
+            degrees[neighbour] -= 1;
+            buckets[degrees[neighbour]].push(neighbour);
+            lowest = lowest.min(degrees[neighbour]);
+        }
+    }
+    order
+}
+
+fn count_cliques(forward: &[BitSet], candidates: &BitSet, k: usize) -> usize {
+    match k {
+        0 => 1,
+        1 => candidates.len(),
+        2 => candidates
+            .iter()
+            .map(|vertex| candidates.intersection_len(&forward[vertex]))
+            .sum(),
+        _ => candidates
+            .iter()
+            .map(|vertex| count_cliques(forward, &candidates.intersection(&forward[vertex]), k - 1))
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts cliques by extending them one vertex at a time, with increasing vertex ids.
+    fn brute_force_cliques(graph: &Graph, k: usize, clique: &mut Vec<usize>) -> usize {
+        if clique.len() == k {
+            return 1;
+        }
+        let start = clique.last().map_or(0, |last| last + 1);
+        let mut count = 0;
+        for vertex in start..graph.len() {
+            if clique.iter().all(|v| graph.neighbours(*v).contains(vertex)) {
+                clique.push(vertex);
+                count += brute_force_cliques(graph, k, clique);
+                clique.pop();
+            }
+        }
+        count
+    }
+
+    fn is_clique(graph: &Graph, vertices: &[usize]) -> bool {
+        vertices.iter().enumerate().all(|(idx, a)| {
+            vertices[idx + 1..]
+                .iter()
+                .all(|b| graph.neighbours(*a).contains(*b))
+        })
+    }
+
+    // Random edges between `n` vertices, plus every edge within `clique`.
+    fn random_edges(n: usize, per_mille: u64, clique: &[usize]) -> Vec<(usize, usize)> {
+        let mut state: u64 = 23;
+        let mut edges = Vec::new();
+        for from in 0..n {
+            for to in from + 1..n {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let planted = clique.contains(&from) && clique.contains(&to);
+                if planted || (state >> 33) % 1000 < per_mille {
+                    edges.push((from, to));
+                }
+            }
+        }
+        edges
+    }
+
+    #[test]
+    fn bitset_across_words() {
+        let mut set = BitSet::new(130);
+        for vertex in [0, 63, 64, 127, 128, 129] {
+            set.insert(vertex);
+        }
+        assert_eq!(set.len(), 6);
+        assert!(set.contains(63) && set.contains(64) && !set.contains(65));
+        set.remove(64);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![0, 63, 127, 128, 129]);
+
+        let mut other = BitSet::new(130);
+        other.insert(63);
+        other.insert(65);
+        assert_eq!(
+            set.intersection(&other).iter().collect::<Vec<_>>(),
+            vec![63]
+        );
+        assert_eq!(set.intersection_len(&other), 1);
+        assert_eq!(set.union(&other).len(), 6);
+        assert_eq!(set.difference(&other).len(), 4);
+        assert_eq!(BitSet::full(130).len(), 130);
+        assert!(BitSet::new(130).is_empty());
+    }
+
+    #[test]
+    fn counts_example_cliques() {
+        let graph = super::super::parse(include_str!("test-input.txt"));
+        let everyone = BitSet::full(graph.len());
+        for (k, expected) in [(1, 16), (2, 32), (3, 12), (4, 1), (5, 0)] {
+            assert_eq!(graph.count_cliques(k, &everyone), expected, "k = {k}");
+            assert_eq!(brute_force_cliques(&graph, k, &mut Vec::new()), expected);
+        }
+
+        // Only ka, co, ta and de make up the 4-clique
+        let without_ka = graph.vertices_where(|name| name != "ka");
+        assert_eq!(graph.count_cliques(4, &without_ka), 0);
+        assert_eq!(graph.count_cliques(1, &without_ka), 15);
+    }
+
+    #[test]
+    fn finds_example_maximum_clique() {
+        let graph = super::super::parse(include_str!("test-input.txt"));
+        let mut names: Vec<_> = graph
+            .maximum_clique()
+            .into_iter()
+            .map(|vertex| graph.name(vertex))
+            .collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["co", "de", "ka", "ta"]);
+    }
+
+    #[test]
+    fn degeneracy_order_is_a_permutation() {
+        let graph = super::super::parse(include_str!("input.txt"));
+        let mut order = graph.degeneracy_order().to_vec();
+        order.sort_unstable();
+        assert_eq!(order, (0..graph.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn more_than_64_vertices() {
+        // The planted clique spans three words of the bitsets
+        let planted = [3, 62, 63, 64, 65, 100, 127, 128, 140];
+        let names: Vec<String> = (0..150).map(|vertex| format!("v{vertex}")).collect();
+        let edges = random_edges(names.len(), 60, &planted);
+        let graph = Graph::from_edges(
+            edges
+                .iter()
+                .map(|(from, to)| (names[*from].as_str(), names[*to].as_str())),
+        );
+        assert_eq!(graph.len(), 150);
+
+        let everyone = BitSet::full(graph.len());
+        for k in 1..=5 {
+            assert_eq!(
+                graph.count_cliques(k, &everyone),
+                brute_force_cliques(&graph, k, &mut Vec::new()),
+                "k = {k}"
+            );
+        }
+
+        let mut maximum: Vec<_> = graph
+            .maximum_clique()
+            .into_iter()
+            .map(|vertex| graph.name(vertex))
+            .collect();
+        maximum.sort_unstable_by_key(|name| name[1..].parse::<usize>().unwrap());
+        let expected: Vec<_> = planted.iter().map(|v| names[*v].as_str()).collect();
+        assert_eq!(maximum, expected);
+
+        // Every maximal clique is a clique that no other vertex can join
+        let mut cliques = 0;
+        graph.for_each_maximal_clique(|clique| {
+            cliques += 1;
+            assert!(is_clique(&graph, clique));
+            let mut extended = clique.to_vec();
+            for vertex in (0..graph.len()).filter(|v| !clique.contains(v)) {
+                extended.push(vertex);
+                assert!(!is_clique(&graph, &extended));
+                extended.pop();
+            }
+        });
+        assert!(cliques > 0);
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+
+use ahash::AHashMap;
+
+use super::netlist::{Netlist, Op};
+
+/// Number of random additions that have to come out right once the swaps are applied.
+const SAMPLES: usize = 1000;
+
+/// The circuit's buses don't have the shape of an adder, so there's nothing to repair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotAnAdder(pub String);
+
+impl fmt::Display for NotAnAdder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Not an adder: {}", self.0)
+    }
+}
+
+impl std::error::Error for NotAnAdder {}
+
+/// Checks that the netlist is a ripple-carry adder, one full-adder stage per bit.
+/// For bit `i`, with `c` the carry out of the previous stage, the stage is
+/// ```text
+/// s = x XOR y      z = s XOR c
+/// a = x AND y      t = s AND c      carry = t OR a
+/// ```
+/// Bit 0 is a half adder, and the last carry is the top `z` wire.
+/// Whenever a stage doesn't match, the two outputs that have to be exchanged to make it match are
+/// swapped and the check carries on. That's enough when swapped wires sit close together in the
+/// adder, as they do in the puzzle. Anything else fails, either structurally or when the repaired
+/// circuit is tested with random additions. Returns the names of the swapped wires, sorted.
+/// A circuit whose buses are the wrong size for an adder fails with `NotAnAdder`.
+pub fn repair<'a>(netlist: &mut Netlist<'a>) -> anyhow::Result<Vec<&'a str>> {
+    let x_bus = netlist.bus_wires('x').to_vec();
+    let y_bus = netlist.bus_wires('y').to_vec();
+    let z_bus = netlist.bus_wires('z').to_vec();
+    let Some(width) = z_bus.len().checked_sub(1).filter(|width| *width > 0) else {
+        return Err(NotAnAdder("An adder needs at least two z wires".to_string()).into());
+    };
+    if x_bus.len() != width || y_bus.len() != width {
+        return Err(NotAnAdder(format!(
+            "{} z wires need {width} x and y wires, found {} and {}",
+            z_bus.len(),
+            x_bus.len(),
+            y_bus.len()
+        ))
+        .into());
+    }
+
+    let mut repair = Repair {
+        index: index(netlist),
+        netlist,
+        swapped: Vec::new(),
+    };
+
+    repair.expect_output(Op::Xor, [x_bus[0], y_bus[0]], z_bus[0])?;
+    let mut carry = repair.find(Op::And, [x_bus[0], y_bus[0]])?;
+    for bit in 1..width {
+        let s = repair.find(Op::Xor, [x_bus[bit], y_bus[bit]])?;
+        let sum = match repair.netlist.driver(z_bus[bit]) {
+            Some(gate)
+                if gate.op == Op::Xor
+                    && (gate.inputs.contains(&s) || gate.inputs.contains(&carry)) =>
+            {
+                gate.inputs
+            }
+            // Some other gate drives the z wire, so the sum has to be found from its inputs
+            _ => {
+                let sum = repair.find_repairing(Op::Xor, [s, carry])?;
+                repair.expect_output(Op::Xor, sum, z_bus[bit])?;
+                sum
+            }
+        };
+        let carry_in = match sum {
+            [left, right] if sorted([left, right]) == sorted([s, carry]) => carry,
+            [left, right] if left == s || right == s => {
+                let other = if left == s { right } else { left };
+                repair.swap(carry, other)?;
+                other
+            }
+            [left, right] if left == carry || right == carry => {
+                let other = if left == carry { right } else { left };
+                repair.swap(s, other)?;
+                carry
+            }
+            [left, right] => anyhow::bail!(
+                "{} is {} XOR {}, which reads neither the sum nor the carry of bit {bit}",
+                repair.netlist.name(z_bus[bit]),
+                repair.netlist.name(left),
+                repair.netlist.name(right)
+            ),
+        };
+
+        // The swaps above may have moved the sum
+        let s = repair.find(Op::Xor, [x_bus[bit], y_bus[bit]])?;
+        let a = repair.find(Op::And, [x_bus[bit], y_bus[bit]])?;
+        let t = repair.find(Op::And, [s, carry_in])?;
+        let [t, a] = repair.find_repairing(Op::Or, [t, a])?;
+        carry = repair.find(Op::Or, [t, a])?;
+    }
+    if carry != z_bus[width] {
+        repair.swap(carry, z_bus[width])?;
+    }
+
+    if let Some((x, y, z)) = repair.netlist.find_wrong_addition(SAMPLES) {
+        anyhow::bail!(
+            "Still not an adder after swapping {}: {x} + {y} = {z}",
+            repair.swapped.join(",")
+        );
+    }
+    let mut swapped = repair.swapped;
+    swapped.sort_unstable();
+    Ok(swapped)
+}
+
+struct Repair<'n, 'a> {
+    netlist: &'n mut Netlist<'a>,
+    // Output of the gate with this operation and inputs, with the inputs sorted.
+    index: AHashMap<(Op, [usize; 2]), usize>,
+    swapped: Vec<&'a str>,
+}
+
+impl<'a> Repair<'_, 'a> {
+    fn find(&self, op: Op, inputs: [usize; 2]) -> anyhow::Result<usize> {
+        self.index
+            .get(&(op, sorted(inputs)))
+            .copied()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "There is no gate {} {op:?} {}",
+                    self.netlist.name(inputs[0]),
+                    self.netlist.name(inputs[1])
+                )
+            })
+    }
+
+    /// Finds the gate reading both `inputs`. If there isn't one, but there is a gate with the same
+    /// operation reading one of them, the other input is in the wrong place and gets swapped in.
+    /// Returns the inputs of the gate that was found.
+    fn find_repairing(&mut self, op: Op, inputs: [usize; 2]) -> anyhow::Result<[usize; 2]> {
+        if self.find(op, inputs).is_ok() {
+            return Ok(inputs);
+        }
+        for (keep, replace) in [(inputs[0], inputs[1]), (inputs[1], inputs[0])] {
+            let other = self.index.keys().find_map(|&(gate_op, gate_inputs)| {
+                let [first, second] = gate_inputs;
+                match (gate_op == op, first == keep, second == keep) {
+                    (true, true, _) => Some(second),
+                    (true, _, true) => Some(first),
+                    _ => None,
+                }
+            });
+            if let Some(other) = other {
+                self.swap(replace, other)?;
+                return Ok([keep, other]);
+            }
+        }
+        self.find(op, inputs).map(|_| inputs)
+    }
+
+    fn expect_output(&mut self, op: Op, inputs: [usize; 2], wire: usize) -> anyhow::Result<()> {
+        let output = self.find(op, inputs)?;
+        if output != wire {
+            self.swap(output, wire)?;
+        }
+        Ok(())
+    }
+
+    fn swap(&mut self, one: usize, two: usize) -> anyhow::Result<()> {
+        let names = [self.netlist.name(one), self.netlist.name(two)];
+        if let Some(name) = names.iter().find(|name| self.swapped.contains(name)) {
+            anyhow::bail!("{name} would have to be swapped twice");
+        }
+        self.netlist.swap_outputs(one, two)?;
+        self.swapped.extend(names);
+        self.index = index(self.netlist);
+        Ok(())
+    }
+}
+
+fn index(netlist: &Netlist) -> AHashMap<(Op, [usize; 2]), usize> {
+    netlist
+        .gates()
+        .iter()
+        .map(|gate| ((gate.op, sorted(gate.inputs)), gate.output))
+        .collect()
+}
+
+fn sorted([left, right]: [usize; 2]) -> [usize; 2] {
+    [left.min(right), left.max(right)]
+}
@@ -0,0 +1,413 @@
+use std::fmt::Write;
+
+use ahash::AHashMap;
+use common::graph::Interner;
+
+use super::Instruction;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Op {
+    And,
+    Or,
+    Xor,
+}
+
+/// A gate reads two wires and drives one. Wires are ids from the netlist's interner.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Gate {
+    pub op: Op,
+    pub inputs: [usize; 2],
+    pub output: usize,
+}
+
+/// The circuit as a graph of wires and gates, with the gates in the order they can be evaluated.
+#[derive(Clone)]
+pub struct Netlist<'a> {
+    wires: Interner<'a>,
+    gates: Vec<Gate>,
+    // Index of the gate driving each wire. Inputs aren't driven by any gate.
+    drivers: Vec<Option<usize>>,
+    order: Vec<usize>,
+    x_bus: Vec<usize>,
+    y_bus: Vec<usize>,
+    z_bus: Vec<usize>,
+}
+
+impl<'a> Netlist<'a> {
+    pub fn new(instructions: &AHashMap<&'a str, Instruction<'a>>) -> anyhow::Result<Netlist<'a>> {
+        let mut netlist = Netlist {
+            wires: Interner::with_capacity(instructions.len() + 100),
+            gates: Vec::with_capacity(instructions.len()),
+            drivers: Vec::new(),
+            order: Vec::new(),
+            x_bus: Vec::new(),
+            y_bus: Vec::new(),
+            z_bus: Vec::new(),
+        };
+        for (output, instruction) in instructions {
+            let (op, left, right) = match *instruction {
+                Instruction::And(l, r) => (Op::And, l, r),
+                Instruction::Or(l, r) => (Op::Or, l, r),
+                Instruction::Xor(l, r) => (Op::Xor, l, r),
+            };
+            let gate = Gate {
+                op,
+                inputs: [netlist.wires.intern(left), netlist.wires.intern(right)],
+                output: netlist.wires.intern(output),
+            };
+            netlist.gates.push(gate);
+        }
+        netlist.x_bus = netlist.bus('x');
+        netlist.y_bus = netlist.bus('y');
+        netlist.z_bus = netlist.bus('z');
+        netlist.sort()?;
+        Ok(netlist)
+    }
+
+    pub fn name(&self, wire: usize) -> &'a str {
+        self.wires.name(wire)
+    }
+
+    pub fn wire(&self, name: &str) -> Option<usize> {
+        self.wires.id(name)
+    }
+
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    pub fn driver(&self, wire: usize) -> Option<&Gate> {
+        self.drivers[wire].map(|gate| &self.gates[gate])
+    }
+
+    /// Wires named `{prefix}00`, `{prefix}01`... ordered from the least significant bit.
+    pub fn bus_wires(&self, prefix: char) -> &[usize] {
+        match prefix {
+            'x' => &self.x_bus,
+            'y' => &self.y_bus,
+            'z' => &self.z_bus,
+            _ => &[],
+        }
+    }
+
+    /// Evaluates every gate, starting from the given input values. Returns the value of each wire.
+    pub fn evaluate(&self, inputs: &AHashMap<&str, u8>) -> Vec<u8> {
+        let mut values = vec![0; self.wires.len()];
+        for (name, value) in inputs {
+            if let Some(wire) = self.wire(name) {
+                values[wire] = *value;
+            }
+        }
+        self.propagate(&mut values);
+        values
+    }
+
+    /// Reads a bus as a number, e.g. all the `z` wires.
+    pub fn read_bus(&self, values: &[u8], prefix: char) -> u64 {
+        self.bus_wires(prefix)
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (bit, wire)| acc | ((values[*wire] as u64) << bit))
+    }
+
+    /// Puts `x` and `y` on the input buses and reads the `z` bus.
+    pub fn add(&self, x: u64, y: u64) -> u64 {
+        let mut values = vec![0; self.wires.len()];
+        for (bus, number) in [(&self.x_bus, x), (&self.y_bus, y)] {
+            for (bit, wire) in bus.iter().enumerate() {
+                values[*wire] = ((number >> bit) & 1) as u8;
+            }
+        }
+        self.propagate(&mut values);
+        self.read_bus(&values, 'z')
+    }
+
+    /// Tries `samples` pseudo-random additions that fit the input width.
+    /// Returns the first `(x, y, z)` where `z != x + y`, ignoring bits beyond the `z` bus.
+    pub fn find_wrong_addition(&self, samples: usize) -> Option<(u64, u64, u64)> {
+        let mask = bit_mask(self.x_bus.len().min(self.y_bus.len()));
+        let z_mask = bit_mask(self.z_bus.len()) as u128;
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+        let mut next = || {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state & mask
+        };
+        // All ones makes every carry ripple through the whole adder
+        [(mask, mask), (mask, 1), (0, 0)]
+            .into_iter()
+            .chain((0..samples).map(|_| (next(), next())))
+            .map(|(x, y)| (x, y, self.add(x, y)))
+            .find(|&(x, y, z)| z as u128 != (x as u128 + y as u128) & z_mask)
+    }
+
+    /// Swaps the outputs of the gates driving `one` and `two`, then re-sorts the gates.
+    /// Fails if either wire is an input, or if the swap creates a cycle.
+    pub fn swap_outputs(&mut self, one: usize, two: usize) -> anyhow::Result<()> {
+        let (Some(first), Some(second)) = (self.drivers[one], self.drivers[two]) else {
+            anyhow::bail!(
+                "Can't swap {} and {}, only gate outputs can be swapped",
+                self.wires.name(one),
+                self.wires.name(two)
+            );
+        };
+        self.gates[first].output = two;
+        self.gates[second].output = one;
+        self.sort()
+    }
+
+    /// Graphviz DOT, with a node for every input wire and every gate.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph circuit {\n    rankdir=LR;\n");
+        for (wire, name) in self.wires.names().iter().enumerate() {
+            if self.drivers[wire].is_none() {
+                writeln!(dot, "    {name} [shape=box];").unwrap();
+            }
+        }
+        for gate in &self.gates {
+            let output = self.wires.name(gate.output);
+            let shape = if output.starts_with('z') {
+                "doublecircle"
+            } else {
+                "ellipse"
+            };
+            writeln!(
+                dot,
+                "    {output} [label=\"{:?}\\n{output}\", shape={shape}];",
+                gate.op
+            )
+            .unwrap();
+            for input in gate.inputs {
+                writeln!(dot, "    {} -> {output};", self.wires.name(input)).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn bus(&self, prefix: char) -> Vec<usize> {
+        let mut bus: Vec<(u32, usize)> = self
+            .wires
+            .names()
+            .iter()
+            .enumerate()
+            .filter_map(|(wire, name)| {
+                let bit = name.strip_prefix(prefix)?.parse().ok()?;
+                Some((bit, wire))
+            })
+            .collect();
+        bus.sort_unstable();
+        bus.into_iter().map(|(_, wire)| wire).collect()
+    }
+
+    // Kahn's algorithm. A gate is ready once the gates driving both its inputs are done.
+    fn sort(&mut self) -> anyhow::Result<()> {
+        self.drivers = vec![None; self.wires.len()];
+        for (idx, gate) in self.gates.iter().enumerate() {
+            if self.drivers[gate.output].replace(idx).is_some() {
+                anyhow::bail!(
+                    "{} is driven by more than one gate",
+                    self.wires.name(gate.output)
+                );
+            }
+        }
+
+        let mut consumers = vec![Vec::new(); self.wires.len()];
+        let mut pending_inputs = vec![0; self.gates.len()];
+        for (idx, gate) in self.gates.iter().enumerate() {
+            for input in gate.inputs {
+                consumers[input].push(idx);
+                if self.drivers[input].is_some() {
+                    pending_inputs[idx] += 1;
+                }
+            }
+        }
+
+        let mut order: Vec<usize> = (0..self.gates.len())
+            .filter(|idx| pending_inputs[*idx] == 0)
+            .collect();
+        let mut next = 0;
+        while let Some(&idx) = order.get(next) {
+            next += 1;
+            for &consumer in &consumers[self.gates[idx].output] {
+                pending_inputs[consumer] -= 1;
+                if pending_inputs[consumer] == 0 {
+                    order.push(consumer);
+                }
+            }
+        }
+
+        if order.len() < self.gates.len() {
+            let cycle = self.find_cycle(&pending_inputs);
+            anyhow::bail!("The circuit has a cycle: {}", cycle.join(" -> "));
+        }
+        self.order = order;
+        Ok(())
+    }
+
+    // Every gate left with pending inputs is on a cycle or downstream of one.
+    // Walking backwards through pending gates has to come back around to a gate already seen.
+    fn find_cycle(&self, pending_inputs: &[usize]) -> Vec<&'a str> {
+        let mut gate = pending_inputs
+            .iter()
+            .position(|pending| *pending > 0)
+            .expect("there is a gate that couldn't be sorted");
+        let mut path = Vec::new();
+        while !path.contains(&gate) {
+            path.push(gate);
+            gate = self.gates[gate]
+                .inputs
+                .iter()
+                .filter_map(|input| self.drivers[*input])
+                .find(|driver| pending_inputs[*driver] > 0)
+                .expect("a pending gate has a pending input");
+        }
+        let start = path.iter().position(|g| *g == gate).unwrap();
+        path[start..]
+            .iter()
+            .rev()
+            .map(|g| self.wires.name(self.gates[*g].output))
+            .collect()
+    }
+
+    fn propagate(&self, values: &mut [u8]) {
+        for &idx in &self.order {
+            let gate = &self.gates[idx];
+            let [left, right] = gate.inputs.map(|input| values[input]);
+            values[gate.output] = match gate.op {
+                Op::And => left & right,
+                Op::Or => left | right,
+                Op::Xor => left ^ right,
+            };
+        }
+    }
+}
+
+fn bit_mask(width: usize) -> u64 {
+    if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{adder, parse};
+    use super::*;
+
+    // A correct ripple-carry adder with `width`-bit inputs, in the puzzle's format.
+    fn ripple_carry_adder(width: usize) -> String {
+        let mut input = String::new();
+        for bus in ['x', 'y'] {
+            for bit in 0..width {
+                writeln!(input, "{bus}{bit:02}: 0").unwrap();
+            }
+        }
+        input.push('\n');
+        writeln!(input, "x00 XOR y00 -> z00").unwrap();
+        writeln!(input, "x00 AND y00 -> c00").unwrap();
+        for bit in 1..width {
+            let carry = if bit == width - 1 {
+                format!("z{width:02}")
+            } else {
+                format!("c{bit:02}")
+            };
+            let previous = bit - 1;
+            writeln!(input, "x{bit:02} XOR y{bit:02} -> s{bit:02}").unwrap();
+            writeln!(input, "y{bit:02} AND x{bit:02} -> a{bit:02}").unwrap();
+            writeln!(input, "s{bit:02} XOR c{previous:02} -> z{bit:02}").unwrap();
+            writeln!(input, "c{previous:02} AND s{bit:02} -> t{bit:02}").unwrap();
+            writeln!(input, "t{bit:02} OR a{bit:02} -> {carry}").unwrap();
+        }
+        input
+    }
+
+    fn netlist(input: &str) -> anyhow::Result<Netlist<'_>> {
+        let (_, instructions) = parse(input);
+        Netlist::new(&instructions)
+    }
+
+    #[test]
+    fn adds_like_an_adder() {
+        for width in [2, 5, 45, 63] {
+            let input = ripple_carry_adder(width);
+            let netlist = netlist(&input).unwrap();
+            assert_eq!(netlist.bus_wires('z').len(), width + 1);
+            assert_eq!(netlist.find_wrong_addition(1000), None, "{width} bits");
+            let mask = bit_mask(width);
+            assert_eq!(netlist.add(mask, mask), 2 * mask);
+            assert_eq!(netlist.add(3, 1), 4);
+        }
+    }
+
+    #[test]
+    fn finds_wrong_additions() {
+        let input = ripple_carry_adder(8).replace("t03 OR a03", "t03 AND a03");
+        let netlist = netlist(&input).unwrap();
+        let (x, y, z) = netlist.find_wrong_addition(1000).unwrap();
+        assert_ne!(x + y, z);
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        let input = "x00: 1\ny00: 0\n\nx00 AND b -> a\na OR y00 -> b\na XOR b -> z00\n";
+        let error = netlist(input).err().unwrap().to_string();
+        assert!(error.starts_with("The circuit has a cycle: "), "{error}");
+        assert!(error.contains('a') && error.contains('b'), "{error}");
+
+        // Swapping a carry with a wire it feeds loops it back on itself
+        let input = ripple_carry_adder(4);
+        let mut netlist = netlist(&input).unwrap();
+        let [c01, t02] = ["c01", "t02"].map(|name| netlist.wire(name).unwrap());
+        let error = netlist.swap_outputs(c01, t02).unwrap_err().to_string();
+        assert!(error.starts_with("The circuit has a cycle: "), "{error}");
+        let x00 = netlist.wire("x00").unwrap();
+        assert!(netlist.swap_outputs(x00, t02).is_err());
+    }
+
+    #[test]
+    fn draws_dot() {
+        let netlist = netlist("x00: 1\ny00: 0\n\nx00 AND y00 -> z00\n").unwrap();
+        assert_eq!(
+            netlist.to_dot(),
+            "digraph circuit {
+    rankdir=LR;
+    x00 [shape=box];
+    y00 [shape=box];
+    z00 [label=\"And\\nz00\", shape=doublecircle];
+    x00 -> z00;
+    y00 -> z00;
+}
+"
+        );
+    }
+
+    #[test]
+    fn repairs_planted_swaps() {
+        let input = ripple_carry_adder(20);
+        for planted in [
+            vec![["s03", "a03"]],
+            vec![
+                ["z05", "t05"],
+                ["z11", "c11"],
+                ["s15", "a15"],
+                ["z17", "z18"],
+            ],
+            vec![["z02", "c02"], ["z09", "a09"], ["z13", "c13"]],
+        ] {
+            let mut netlist = netlist(&input).unwrap();
+            for [one, two] in &planted {
+                let [one, two] = [one, two].map(|name| netlist.wire(name).unwrap());
+                netlist.swap_outputs(one, two).unwrap();
+            }
+            assert!(netlist.find_wrong_addition(1000).is_some());
+            let mut expected: Vec<&str> = planted.iter().flatten().copied().collect();
+            expected.sort_unstable();
+            assert_eq!(adder::repair(&mut netlist).unwrap(), expected);
+            assert_eq!(netlist.find_wrong_addition(1000), None);
+        }
+    }
+}
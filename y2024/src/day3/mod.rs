@@ -1,36 +1,20 @@
 use regex::Regex;
 
-use crate::computer::Instruction;
+use crate::computer::{self, Computer, Interpreter};
 
 #[inline]
 pub fn part1(input: &str) -> i32 {
-    let mut result = 0;
-    let mut computer = crate::computer::Computer::init(input);
-    while let Some(instruction) = computer.next_instruction() {
-        if let Instruction::Mul(x, y) = instruction {
-            result += x * y;
-        }
-    }
-    result
+    let mut interpreter = Interpreter::new();
+    interpreter.add_handler(computer::multiply);
+    interpreter.run(Computer::init(input)).result
 }
 
 #[inline]
 pub fn part2(input: &str) -> i32 {
-    let mut result = 0;
-    let mut computer = crate::computer::Computer::init(input);
-    while let Some(instruction) = computer.next_instruction() {
-        match instruction {
-            Instruction::Mul(x, y) => {
-                if computer.multiply_enabled {
-                    result += x * y
-                }
-            }
-            Instruction::Do => computer.multiply_enabled = true,
-            Instruction::Dont => computer.multiply_enabled = false,
-            _ => {}
-        }
-    }
-    result
+    let mut interpreter = Interpreter::new();
+    interpreter.add_handler(computer::conditionals);
+    interpreter.add_handler(computer::multiply);
+    interpreter.run(Computer::init(input)).result
 }
 
 #[allow(dead_code)]
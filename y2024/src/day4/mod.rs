@@ -1,5 +1,8 @@
 use common::grid::Grid;
 
+pub mod search;
+
+/// `search` finds any words and stencils, but XMAS and the X of MAS keep their own loops, which are faster.
 #[inline]
 pub fn part1(input: &str) -> u32 {
     let grid = parse(input);
@@ -0,0 +1,384 @@
+use common::grid::{Grid, Point};
+
+/// How far one letter is from the previous one, in rows and columns.
+pub type Offset = (i32, i32);
+
+pub const HORIZONTAL_AND_VERTICAL: [Offset; 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+pub const DIAGONAL: [Offset; 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+pub const ALL_DIRECTIONS: [Offset; 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// A word found in the grid, starting at `start` and reading in `direction`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WordMatch<'w> {
+    pub word: &'w str,
+    pub start: Point,
+    pub direction: Offset,
+}
+
+/// Every place any of `words` can be read in one of `directions`.
+/// A word that reads the same both ways is found once for each way.
+pub fn find_words<'w>(
+    grid: &Grid<char>,
+    words: &[&'w str],
+    directions: &[Offset],
+) -> Vec<WordMatch<'w>> {
+    let words: Vec<(&str, Vec<char>)> = words
+        .iter()
+        .filter(|word| !word.is_empty())
+        .map(|word| (*word, word.chars().collect()))
+        .collect();
+    let mut matches = Vec::new();
+    for row in 0..grid.rows {
+        for column in 0..grid.columns {
+            let start = (row, column);
+            let c = grid.get(start);
+            for (word, letters) in &words {
+                if Some(letters[0]) != c {
+                    continue;
+                }
+                for direction in directions {
+                    // Only the last letter needs checking against the edges of the grid
+                    let end = offset(start, *direction, letters.len() as i32 - 1);
+                    if !contains(grid, end) {
+                        continue;
+                    }
+                    // Checking every letter instead of stopping at the first mismatch avoids a
+                    // branch per letter
+                    let reads =
+                        letters
+                            .iter()
+                            .enumerate()
+                            .skip(1)
+                            .fold(true, |reads, (idx, letter)| {
+                                let (row, column) = offset(start, *direction, idx as i32);
+                                reads & (grid.get((row as usize, column as usize)) == Some(*letter))
+                            });
+                    if reads {
+                        matches.push(WordMatch {
+                            word,
+                            start,
+                            direction: *direction,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    matches
+}
+
+/// A 2D pattern of letters, one string per row. `WILDCARD` matches any letter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stencil {
+    rows: usize,
+    columns: usize,
+    // The letters that have to match, relative to the top left corner
+    letters: Vec<(Offset, char)>,
+}
+
+pub const WILDCARD: char = '.';
+
+/// A stencil found in the grid, with its top left corner at `position` once turned clockwise by
+/// `quarter_turns`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StencilMatch {
+    pub position: Point,
+    pub quarter_turns: u8,
+}
+
+impl Stencil {
+    /// Rows shorter than the longest one are padded with wildcards.
+    pub fn new(rows: &[&str]) -> Stencil {
+        let letters = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c != WILDCARD)
+                    .map(move |(column, c)| ((row as i32, column as i32), c))
+            })
+            .collect();
+        Stencil {
+            rows: rows.len(),
+            columns: rows
+                .iter()
+                .map(|row| row.chars().count())
+                .max()
+                .unwrap_or(0),
+            letters,
+        }
+    }
+
+    /// The same stencil turned a quarter turn clockwise.
+    pub fn rotated(&self) -> Stencil {
+        let mut letters: Vec<_> = self
+            .letters
+            .iter()
+            .map(|((row, column), c)| ((*column, self.rows as i32 - 1 - row), *c))
+            .collect();
+        letters.sort_unstable();
+        Stencil {
+            rows: self.columns,
+            columns: self.rows,
+            letters,
+        }
+    }
+
+    /// Each different way the stencil can be turned, with how many quarter turns it took.
+    /// A symmetric stencil has fewer than four.
+    pub fn rotations(&self) -> Vec<(u8, Stencil)> {
+        let mut rotations: Vec<(u8, Stencil)> = Vec::with_capacity(4);
+        let mut stencil = self.clone();
+        stencil.letters.sort_unstable();
+        for quarter_turns in 0..4 {
+            if rotations.iter().all(|(_, seen)| *seen != stencil) {
+                rotations.push((quarter_turns, stencil.clone()));
+            }
+            stencil = stencil.rotated();
+        }
+        rotations
+    }
+
+    pub fn matches_at(&self, grid: &Grid<char>, position: Point) -> bool {
+        self.fits(grid, position, &self.letters)
+    }
+
+    // Whether the stencil is inside the grid at `position` and each of `letters` matches.
+    fn fits(&self, grid: &Grid<char>, position: Point, letters: &[(Offset, char)]) -> bool {
+        // Checking every letter instead of stopping at the first mismatch avoids a branch per
+        // letter, as in find_words
+        position.0 + self.rows <= grid.rows
+            && position.1 + self.columns <= grid.columns
+            && letters.iter().fold(true, |fits, ((row, column), c)| {
+                let position = (position.0 + *row as usize, position.1 + *column as usize);
+                fits & (grid.get(position) == Some(*c))
+            })
+    }
+}
+
+/// Every place the stencil fits in the grid. With `rotate`, each of its rotations is tried too.
+/// Each rotation is only tried where the grid has the stencil's rarest letter.
+pub fn find_stencil(grid: &Grid<char>, stencil: &Stencil, rotate: bool) -> Vec<StencilMatch> {
+    let rotations = if rotate {
+        stencil.rotations()
+    } else {
+        vec![(0, stencil.clone())]
+    };
+    // How often each of the stencil's letters appears in the grid
+    let mut frequencies: Vec<(char, usize)> = Vec::new();
+    for (_, c) in &stencil.letters {
+        if frequencies.iter().all(|(seen, _)| seen != c) {
+            frequencies.push((*c, 0));
+        }
+    }
+    for row in 0..grid.rows {
+        for column in 0..grid.columns {
+            let c = grid.get((row, column));
+            for (letter, count) in &mut frequencies {
+                *count += (Some(*letter) == c) as usize;
+            }
+        }
+    }
+    // Each rotation with the offset and letter of its anchor, and the letters left to check once
+    // the anchor matches. Nothing but wildcards fits everywhere it's small enough to, so those
+    // have no anchor.
+    let anchored: Vec<_> = rotations
+        .iter()
+        .map(|(quarter_turns, stencil)| {
+            let anchor = stencil
+                .letters
+                .iter()
+                .min_by_key(|(_, c)| {
+                    frequencies
+                        .iter()
+                        .find(|(letter, _)| letter == c)
+                        .map(|(_, count)| *count)
+                })
+                .copied();
+            let rest: Vec<_> = stencil
+                .letters
+                .iter()
+                .filter(|letter| Some(**letter) != anchor)
+                .copied()
+                .collect();
+            (*quarter_turns, stencil, anchor, rest)
+        })
+        .collect();
+    let mut matches = Vec::new();
+    for row in 0..grid.rows {
+        for column in 0..grid.columns {
+            let c = grid.get((row, column));
+            for (quarter_turns, stencil, anchor, rest) in &anchored {
+                let position = match anchor {
+                    None => (row, column),
+                    Some((_, letter)) if Some(*letter) != c => continue,
+                    Some(((dr, dc), _)) => {
+                        let (Some(top), Some(left)) = (
+                            row.checked_sub(*dr as usize),
+                            column.checked_sub(*dc as usize),
+                        ) else {
+                            continue;
+                        };
+                        (top, left)
+                    }
+                };
+                if stencil.fits(grid, position, rest) {
+                    matches.push(StencilMatch {
+                        position,
+                        quarter_turns: *quarter_turns,
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn contains(grid: &Grid<char>, (row, column): (i32, i32)) -> bool {
+    (0..grid.rows as i32).contains(&row) && (0..grid.columns as i32).contains(&column)
+}
+
+fn offset(start: Point, (dr, dc): Offset, times: i32) -> (i32, i32) {
+    (start.0 as i32 + dr * times, start.1 as i32 + dc * times)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_INPUT: &str = include_str!("test-input.txt");
+    static FULL_INPUT: &str = include_str!("input.txt");
+
+    fn grid(rows: &[&str]) -> Grid<char> {
+        super::super::parse(&rows.join("\n"))
+    }
+
+    fn turns(stencil: &Stencil) -> Vec<u8> {
+        stencil
+            .rotations()
+            .iter()
+            .map(|(turns, _)| *turns)
+            .collect()
+    }
+
+    #[test]
+    fn agrees_with_the_puzzle() {
+        let x_mas = Stencil::new(&["M.S", ".A.", "M.S"]);
+        for input in [TEST_INPUT, FULL_INPUT] {
+            let grid = super::super::parse(input);
+            assert_eq!(
+                find_words(&grid, &["XMAS"], &ALL_DIRECTIONS).len() as u32,
+                super::super::part1(input)
+            );
+            assert_eq!(
+                find_stencil(&grid, &x_mas, true).len() as i32,
+                super::super::part2(input)
+            );
+        }
+    }
+
+    #[test]
+    fn only_reads_in_the_given_directions() {
+        let grid = grid(&["XMAS", "MM..", "A.A.", "S..S"]);
+        let found = |directions: &[Offset]| find_words(&grid, &["XMAS"], directions);
+        assert_eq!(found(&ALL_DIRECTIONS).len(), 3);
+        assert_eq!(found(&DIAGONAL).len(), 1);
+        assert_eq!(
+            found(&HORIZONTAL_AND_VERTICAL),
+            vec![
+                WordMatch {
+                    word: "XMAS",
+                    start: (0, 0),
+                    direction: (0, 1)
+                },
+                WordMatch {
+                    word: "XMAS",
+                    start: (0, 0),
+                    direction: (1, 0)
+                },
+            ]
+        );
+        assert!(found(&[(0, -1), (-1, 0)]).is_empty());
+        assert!(find_words(&grid, &[""], &ALL_DIRECTIONS).is_empty());
+    }
+
+    #[test]
+    fn finds_single_letters_and_palindromes() {
+        let grid = grid(&["ABA", "BAB"]);
+        // A single letter reads the same in every direction
+        assert_eq!(find_words(&grid, &["A"], &ALL_DIRECTIONS).len(), 3 * 8);
+        assert_eq!(find_words(&grid, &["A"], &[(0, 1)]).len(), 3);
+        // ABA is found from both ends
+        let found = find_words(&grid, &["ABA"], &HORIZONTAL_AND_VERTICAL);
+        let starts: Vec<_> = found.iter().map(|m| (m.start, m.direction)).collect();
+        assert_eq!(starts, vec![((0, 0), (0, 1)), ((0, 2), (0, -1))]);
+        let found = find_words(&grid, &["ABA", "BAB", "AB"], &[(0, 1)]);
+        let words: Vec<_> = found.iter().map(|m| m.word).collect();
+        assert_eq!(words, vec!["ABA", "AB", "BAB", "AB"]);
+    }
+
+    #[test]
+    fn wildcards_match_anything() {
+        let grid = grid(&["ABC", "DEF", "GHI"]);
+        let corners = Stencil::new(&["A.C", "...", "G.I"]);
+        assert_eq!(
+            find_stencil(&grid, &corners, false),
+            vec![StencilMatch {
+                position: (0, 0),
+                quarter_turns: 0
+            }]
+        );
+        assert!(corners.matches_at(&grid, (0, 0)));
+        assert!(!corners.matches_at(&grid, (0, 1)));
+        // Only wildcards, and a short row padded with them
+        assert_eq!(
+            find_stencil(&grid, &Stencil::new(&["..", "."]), true).len(),
+            4
+        );
+        assert_eq!(
+            find_stencil(&grid, &Stencil::new(&["B", "EF"]), false).len(),
+            1
+        );
+        assert!(find_stencil(&grid, &Stencil::new(&["....", "A"]), false).is_empty());
+    }
+
+    #[test]
+    fn finds_each_rotation_once() {
+        // The X of MAS looks different after each quarter turn
+        let x_mas = Stencil::new(&["M.S", ".A.", "M.S"]);
+        assert_eq!(turns(&x_mas), vec![0, 1, 2, 3]);
+        assert_eq!(
+            x_mas.rotated().rotated().rotated().rotated(),
+            x_mas.rotations()[0].1
+        );
+        // A line is the same after half a turn, and a plus sign after any turn
+        let line = Stencil::new(&["ABA"]);
+        assert_eq!(turns(&line), vec![0, 1]);
+        assert_eq!(turns(&Stencil::new(&[".A.", "ABA", ".A."])), vec![0]);
+
+        let grid = grid(&["ABA", "B.B", "ABA"]);
+        let mut found: Vec<_> = find_stencil(&grid, &line, true)
+            .into_iter()
+            .map(|m| (m.position, m.quarter_turns))
+            .collect();
+        found.sort_unstable();
+        assert_eq!(
+            found,
+            vec![((0, 0), 0), ((0, 0), 1), ((0, 2), 1), ((2, 0), 0)]
+        );
+        // Turning a stencil that isn't square swaps its rows and columns
+        let pair = Stencil::new(&["AB"]);
+        assert_eq!(find_stencil(&grid, &pair, false).len(), 2);
+        assert_eq!(find_stencil(&grid, &pair, true).len(), 8);
+    }
+}
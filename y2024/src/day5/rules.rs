@@ -0,0 +1,356 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use ahash::AHashMap;
+use common::graph::{AdjacencyList, Cycle};
+
+/// Page ordering rules, each saying one page has to be printed before another if both are in an
+/// update. The rules only have to be consistent for the pages of any one update; across every
+/// page they can, and in the puzzle do, go round in circles.
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    // Every page in a rule gets an id, counting up from 0
+    ids: AHashMap<u32, usize>,
+    // A bit matrix with a row for each id, `words` u64s long. Bit `second` of row `first` is set
+    // if `first` has to come before `second`.
+    words: usize,
+    before: Vec<u64>,
+}
+
+/// Rules that can't all be followed, because they go round in a circle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inconsistent {
+    /// Each page has to come before the next, and the last before the first.
+    pub cycle: Vec<u32>,
+}
+
+impl fmt::Display for Inconsistent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The rules go round in a circle: ")?;
+        for page in &self.cycle {
+            write!(f, "{page} -> ")?;
+        }
+        match self.cycle.first() {
+            Some(first) => write!(f, "{first}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for Inconsistent {}
+
+impl Rules {
+    /// Each pair is a page that has to come before another.
+    pub fn new(rules: impl IntoIterator<Item = (u32, u32)>) -> Rules {
+        let mut ids = AHashMap::new();
+        let mut id = |page| {
+            let next = ids.len();
+            *ids.entry(page).or_insert(next)
+        };
+        let rules: Vec<_> = rules
+            .into_iter()
+            .map(|(first, second)| (id(first), id(second)))
+            .collect();
+        let words = ids.len().div_ceil(64);
+        let mut before = vec![0; ids.len() * words];
+        for (first, second) in rules {
+            before[first * words + second / 64] |= 1 << (second % 64);
+        }
+        Rules { ids, words, before }
+    }
+
+    pub fn must_precede(&self, first: u32, second: u32) -> bool {
+        match (self.ids.get(&first), self.ids.get(&second)) {
+            (Some(first), Some(second)) => self.precedes(*first, *second),
+            _ => false,
+        }
+    }
+
+    /// The rules that apply to an update, as a graph over positions in the update with an edge
+    /// from each page to every page that has to come after it.
+    pub fn graph(&self, update: &[u32]) -> AdjacencyList {
+        let ids = self.ids(update);
+        let mut graph = AdjacencyList::new(update.len());
+        for (from, first) in ids.iter().enumerate() {
+            for (to, second) in ids.iter().enumerate() {
+                if let (Some(first), Some(second)) = (first, second)
+                    && self.precedes(*first, *second)
+                {
+                    graph.add_edge(from, to);
+                }
+            }
+        }
+        graph
+    }
+
+    pub fn is_ordered(&self, update: &[u32]) -> bool {
+        let ids: Vec<usize> = self.ids(update).into_iter().flatten().collect();
+        ids.iter().enumerate().all(|(idx, later)| {
+            ids[..idx]
+                .iter()
+                .all(|earlier| !self.precedes(*later, *earlier))
+        })
+    }
+
+    /// The update's pages in an order that follows the rules. Pages the rules don't order keep
+    /// their original order, so an update that's already ordered comes back unchanged.
+    /// Pages are assumed to appear only once per update.
+    pub fn order(&self, update: &[u32]) -> Result<Vec<u32>, Inconsistent> {
+        let order = self.positions(update)?;
+        Ok(order.into_iter().map(|idx| update[idx]).collect())
+    }
+
+    /// The same as `order` when the rules order every pair of pages in the update, as they do in
+    /// the puzzle, found by sorting with the rules as the comparator. That's faster, but with
+    /// pages the rules don't order it can break a rule, so it's None unless the order it finds is
+    /// the only one.
+    pub fn sort(&self, update: &[u32]) -> Option<Vec<u32>> {
+        let mut pages = update
+            .iter()
+            .map(|page| Some((*self.ids.get(page)?, *page)))
+            .collect::<Option<Vec<(usize, u32)>>>()?;
+        pages.sort_unstable_by(|(first, _), (second, _)| {
+            if self.precedes(*first, *second) {
+                Ordering::Less
+            } else if self.precedes(*second, *first) {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        });
+        // Each page has to come before the next, and with no rule broken further apart that's
+        // the only order there is
+        let only = pages.iter().enumerate().all(|(position, (later, _))| {
+            pages[..position]
+                .iter()
+                .all(|(earlier, _)| !self.precedes(*later, *earlier))
+        }) && pages
+            .windows(2)
+            .all(|pair| self.precedes(pair[0].0, pair[1].0));
+        only.then(|| pages.into_iter().map(|(_, page)| page).collect())
+    }
+
+    /// Swaps of two positions which, made one after another, turn the update into `order(update)`.
+    /// Every swap puts at least one page where it belongs, which makes the number of swaps the
+    /// length of the update less its number of permutation cycles, the fewest possible.
+    /// When the rules pin down a single order, as they do in the puzzle, that's the fewest swaps
+    /// to fix the update at all.
+    pub fn swaps(&self, update: &[u32]) -> Result<Vec<(usize, usize)>, Inconsistent> {
+        let order = self.positions(update)?;
+        // target[idx] is the position the page now at idx has to move to
+        let mut target = vec![0; update.len()];
+        for (position, idx) in order.into_iter().enumerate() {
+            target[idx] = position;
+        }
+        let mut swaps = Vec::new();
+        for idx in 0..target.len() {
+            while target[idx] != idx {
+                let other = target[idx];
+                swaps.push((idx, other));
+                target.swap(idx, other);
+            }
+        }
+        Ok(swaps)
+    }
+
+    fn precedes(&self, first: usize, second: usize) -> bool {
+        self.before[first * self.words + second / 64] & (1 << (second % 64)) != 0
+    }
+
+    // Pages that aren't in any rule have no id.
+    fn ids(&self, update: &[u32]) -> Vec<Option<usize>> {
+        update
+            .iter()
+            .map(|page| self.ids.get(page).copied())
+            .collect()
+    }
+
+    // Positions in the update, in the order their pages should be. This is Kahn's algorithm on
+    // the bit matrix, taking the first ready position each time so the original order wins ties,
+    // the same as the graph's topological sort. The graph is only built to report a cycle.
+    fn positions(&self, update: &[u32]) -> Result<Vec<usize>, Inconsistent> {
+        let ids = self.ids(update);
+        let precedes = |from: usize, to: usize| match (ids[from], ids[to]) {
+            (Some(first), Some(second)) => self.precedes(first, second),
+            _ => false,
+        };
+        // Number of pages not yet placed that have to come before each position
+        let mut waiting: Vec<usize> = (0..update.len())
+            .map(|to| (0..update.len()).filter(|from| precedes(*from, to)).count())
+            .collect();
+        // When the rules order every pair of pages, as they do in the puzzle, that number is the
+        // page's position and there's no need to go step by step. It's only a total order if no
+        // page ends up before one that has to precede it.
+        let mut order = vec![usize::MAX; update.len()];
+        for (idx, before) in waiting.iter().enumerate() {
+            if let Some(slot) = order.get_mut(*before) {
+                *slot = idx;
+            }
+        }
+        if order.iter().all(|idx| *idx != usize::MAX)
+            && order.iter().enumerate().all(|(position, later)| {
+                order[..position]
+                    .iter()
+                    .all(|earlier| !precedes(*later, *earlier))
+            })
+        {
+            return Ok(order);
+        }
+
+        let mut placed = vec![false; update.len()];
+        order.clear();
+        while order.len() < update.len() {
+            let Some(next) = (0..update.len()).find(|idx| !placed[*idx] && waiting[*idx] == 0)
+            else {
+                return Err(self.cycle(update));
+            };
+            placed[next] = true;
+            order.push(next);
+            for (to, waiting) in waiting.iter_mut().enumerate() {
+                if precedes(next, to) {
+                    *waiting -= 1;
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    fn cycle(&self, update: &[u32]) -> Inconsistent {
+        match self.graph(update).topological_sort() {
+            Err(Cycle(cycle)) => Inconsistent {
+                cycle: cycle.into_iter().map(|idx| update[idx]).collect(),
+            },
+            Ok(_) => unreachable!("the pages could only be partly ordered"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_INPUT: &str = include_str!("test-input.txt");
+    static FULL_INPUT: &str = include_str!("input.txt");
+
+    fn apply(update: &[u32], swaps: &[(usize, usize)]) -> Vec<u32> {
+        let mut update = update.to_vec();
+        for (one, two) in swaps {
+            update.swap(*one, *two);
+        }
+        update
+    }
+
+    #[test]
+    fn orders_the_example() {
+        let (_, rules) = super::super::parse(TEST_INPUT);
+        assert!(rules.must_precede(47, 53));
+        assert!(!rules.must_precede(53, 47));
+        assert!(!rules.must_precede(47, 1000));
+        assert!(rules.is_ordered(&[75, 47, 61, 53, 29]));
+        assert!(!rules.is_ordered(&[97, 13, 75, 29, 47]));
+        assert_eq!(
+            rules.order(&[97, 13, 75, 29, 47]),
+            Ok(vec![97, 75, 47, 29, 13])
+        );
+        assert_eq!(rules.swaps(&[75, 47, 61, 53, 29]), Ok(vec![]));
+        assert_eq!(rules.swaps(&[61, 13, 29]), Ok(vec![(1, 2)]));
+    }
+
+    #[test]
+    fn swaps_give_the_order() {
+        for input in [TEST_INPUT, FULL_INPUT] {
+            let (books, rules) = super::super::parse(input);
+            for book in &books {
+                let order = rules.order(book).unwrap();
+                let swaps = rules.swaps(book).unwrap();
+                assert_eq!(apply(book, &swaps), order);
+                assert!(rules.is_ordered(&order));
+                assert_eq!(swaps.is_empty(), rules.is_ordered(book));
+                // Each swap puts at least one page in place
+                assert!(swaps.len() < book.len());
+            }
+        }
+    }
+
+    #[test]
+    fn matches_the_graph() {
+        let (books, rules) = super::super::parse(FULL_INPUT);
+        for book in &books {
+            let graph_order: Vec<u32> = rules
+                .graph(book)
+                .topological_sort()
+                .unwrap()
+                .into_iter()
+                .map(|idx| book[idx])
+                .collect();
+            assert_eq!(rules.order(book).unwrap(), graph_order);
+        }
+    }
+
+    #[test]
+    fn keeps_the_order_of_unrelated_pages() {
+        let rules = Rules::new([(1, 2), (3, 4)]);
+        assert_eq!(rules.order(&[9, 4, 2, 3, 1]), Ok(vec![9, 3, 4, 1, 2]));
+        // Each time, the first page that's free to go next does
+        assert_eq!(rules.order(&[4, 9, 2, 3, 1]), Ok(vec![9, 3, 4, 1, 2]));
+        assert_eq!(rules.order(&[2, 9, 1, 3]), Ok(vec![9, 1, 2, 3]));
+        assert_eq!(rules.order(&[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let rules = Rules::new([(1, 2), (2, 3), (3, 1)]);
+        let error = rules.order(&[1, 2, 3]).unwrap_err();
+        // The cycle can start anywhere, but has to follow the rules around
+        let start = error.cycle.iter().position(|page| *page == 1).unwrap();
+        let mut cycle = error.cycle.clone();
+        cycle.rotate_left(start);
+        assert_eq!(cycle, vec![1, 2, 3]);
+        assert_eq!(
+            Inconsistent {
+                cycle: vec![1, 2, 3]
+            }
+            .to_string(),
+            "The rules go round in a circle: 1 -> 2 -> 3 -> 1"
+        );
+        assert!(rules.swaps(&[3, 2, 1]).is_err());
+
+        // Only the pages in the update matter
+        assert_eq!(rules.order(&[3, 2]), Ok(vec![2, 3]));
+        let rules = Rules::new([(1, 2), (2, 3), (3, 1), (4, 5), (5, 4)]);
+        assert_eq!(rules.order(&[5, 1, 4]).unwrap_err().cycle.len(), 2);
+
+        // Counting predecessors gives every page a different position, but 3 and 2 still disagree
+        let rules = Rules::new([(1, 3), (2, 3), (3, 2)]);
+        let mut cycle = rules.order(&[1, 2, 3]).unwrap_err().cycle;
+        cycle.sort_unstable();
+        assert_eq!(cycle, vec![2, 3]);
+    }
+
+    #[test]
+    fn sort_agrees_with_order() {
+        for input in [TEST_INPUT, FULL_INPUT] {
+            let (books, rules) = super::super::parse(input);
+            for book in &books {
+                assert_eq!(rules.sort(book), Some(rules.order(book).unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn sort_needs_a_single_order() {
+        // 1 and 3 could go either way round
+        let rules = Rules::new([(1, 2), (3, 2)]);
+        assert_eq!(rules.sort(&[2, 1, 3]), None);
+        assert_eq!(rules.sort(&[2, 1]), Some(vec![1, 2]));
+        // Pages without rules can go anywhere
+        assert_eq!(rules.sort(&[2, 9, 1]), None);
+
+        // With a cycle, some rule is broken whatever the order
+        let rules = Rules::new([(1, 2), (2, 3), (3, 1)]);
+        assert_eq!(rules.sort(&[1, 2, 3]), None);
+        let rules = Rules::new([(1, 2), (2, 3), (3, 1), (1, 3)]);
+        assert_eq!(rules.sort(&[3, 2, 1]), None);
+        assert_eq!(rules.sort(&[]), Some(vec![]));
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt;
+
+use common::math::checked_next_power;
+
+/// An operator placed between two operands. Equations are always evaluated left to right,
+/// ignoring precedence.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Operator {
+    Add,
+    /// Only while the result stays non-negative.
+    Sub,
+    Mul,
+    /// Only when it divides exactly.
+    Div,
+    /// Writes the digits of the right operand after the left one, in the given base.
+    Concat(u64),
+}
+
+pub const PART_1: [Operator; 2] = [Operator::Add, Operator::Mul];
+pub const PART_2: [Operator; 3] = [Operator::Add, Operator::Mul, Operator::Concat(10)];
+
+// What the left operand had to be for an operator to give a certain result.
+enum Left {
+    Exactly(u64),
+    // Multiplying by 0 gives 0 whatever the left operand is
+    Anything,
+}
+
+impl Operator {
+    /// None if the operation isn't defined for these operands or the result doesn't fit a u64.
+    pub fn apply(self, left: u64, right: u64) -> Option<u64> {
+        match self {
+            Operator::Add => left.checked_add(right),
+            Operator::Sub => left.checked_sub(right),
+            Operator::Mul => left.checked_mul(right),
+            Operator::Div => (right != 0 && left.is_multiple_of(right)).then(|| left / right),
+            Operator::Concat(base) => left
+                .checked_mul(checked_next_power(right, base)?)?
+                .checked_add(right),
+        }
+    }
+
+    fn undo(self, result: u64, right: u64) -> Option<Left> {
+        let left = match self {
+            Operator::Add => result.checked_sub(right)?,
+            Operator::Sub => result.checked_add(right)?,
+            Operator::Mul if right == 0 => return (result == 0).then_some(Left::Anything),
+            Operator::Mul => result.is_multiple_of(right).then(|| result / right)?,
+            Operator::Div if right == 0 => return None,
+            Operator::Div => result.checked_mul(right)?,
+            Operator::Concat(base) => {
+                let power = checked_next_power(right, base)?;
+                let prefix = result.checked_sub(right)?;
+                prefix.is_multiple_of(power).then(|| prefix / power)?
+            }
+        };
+        Some(Left::Exactly(left))
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operator::Add => write!(f, "+"),
+            Operator::Sub => write!(f, "-"),
+            Operator::Mul => write!(f, "*"),
+            Operator::Div => write!(f, "/"),
+            Operator::Concat(10) => write!(f, "||"),
+            Operator::Concat(base) => write!(f, "||{base}"),
+        }
+    }
+}
+
+/// Operators that turn `operands` into `target`, one fewer than there are operands.
+/// Searches backwards from the target, undoing the last operator first. Most operators can only
+/// be undone one way, and often not at all, which prunes far more than trying them forwards.
+/// Returns None if no choice of operators works. Panics if a concat base is below 2.
+pub fn solve(operators: &[Operator], target: u64, operands: &[u64]) -> Option<Vec<Operator>> {
+    assert!(
+        operators
+            .iter()
+            .all(|op| !matches!(op, Operator::Concat(base) if *base < 2)),
+        "concat needs a base of at least 2"
+    );
+    let mut chosen = Vec::with_capacity(operands.len().saturating_sub(1));
+    backwards(operators, target, operands, &mut chosen).then(|| {
+        chosen.reverse();
+        chosen
+    })
+}
+
+/// Writes out the equation, as in `81 * 40 + 27`.
+pub fn render(operands: &[u64], operators: &[Operator]) -> String {
+    let mut equation = operands.first().map(u64::to_string).unwrap_or_default();
+    for (op, operand) in operators.iter().zip(&operands[1..]) {
+        equation.push_str(&format!(" {op} {operand}"));
+    }
+    equation
+}
+
+// Pushes the operators it picks, last one first.
+fn backwards(
+    operators: &[Operator],
+    target: u64,
+    operands: &[u64],
+    chosen: &mut Vec<Operator>,
+) -> bool {
+    let Some((last, rest)) = operands.split_last() else {
+        return false;
+    };
+    if rest.is_empty() {
+        return *last == target;
+    }
+    for op in operators {
+        chosen.push(*op);
+        let found = match op.undo(target, *last) {
+            None => false,
+            Some(Left::Exactly(left)) => backwards(operators, left, rest, chosen),
+            Some(Left::Anything) => forwards(operators, rest)
+                .map(|prefix| chosen.extend(prefix.into_iter().rev()))
+                .is_some(),
+        };
+        if found {
+            return true;
+        }
+        chosen.pop();
+    }
+    false
+}
+
+// Any operators that can be applied to every operand without failing.
+fn forwards(operators: &[Operator], operands: &[u64]) -> Option<Vec<Operator>> {
+    fn search(
+        operators: &[Operator],
+        value: u64,
+        operands: &[u64],
+        chosen: &mut Vec<Operator>,
+    ) -> bool {
+        let Some((next, rest)) = operands.split_first() else {
+            return true;
+        };
+        operators.iter().any(|op| {
+            let Some(value) = op.apply(value, *next) else {
+                return false;
+            };
+            chosen.push(*op);
+            search(operators, value, rest, chosen) || {
+                chosen.pop();
+                false
+            }
+        })
+    }
+    let (first, rest) = operands.split_first()?;
+    let mut chosen = Vec::new();
+    search(operators, *first, rest, &mut chosen).then_some(chosen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Operator; 6] = [
+        Operator::Add,
+        Operator::Sub,
+        Operator::Mul,
+        Operator::Div,
+        Operator::Concat(2),
+        Operator::Concat(10),
+    ];
+
+    fn evaluate(operands: &[u64], chosen: &[Operator]) -> Option<u64> {
+        assert_eq!(chosen.len() + 1, operands.len());
+        chosen
+            .iter()
+            .zip(&operands[1..])
+            .try_fold(operands[0], |left, (op, right)| op.apply(left, *right))
+    }
+
+    // Tries every sequence of operators, forwards.
+    fn brute_force(operators: &[Operator], target: u64, operands: &[u64]) -> bool {
+        let slots = operands.len() - 1;
+        (0..operators.len().pow(slots as u32)).any(|mut idx| {
+            let chosen: Vec<_> = (0..slots)
+                .map(|_| {
+                    let op = operators[idx % operators.len()];
+                    idx /= operators.len();
+                    op
+                })
+                .collect();
+            evaluate(operands, &chosen) == Some(target)
+        })
+    }
+
+    fn check(operators: &[Operator], target: u64, operands: &[u64]) {
+        let solved = solve(operators, target, operands);
+        if let Some(chosen) = &solved {
+            assert!(chosen.iter().all(|op| operators.contains(op)));
+            assert_eq!(
+                evaluate(operands, chosen),
+                Some(target),
+                "{} = {target}",
+                render(operands, chosen)
+            );
+        }
+        assert_eq!(
+            solved.is_some(),
+            brute_force(operators, target, operands),
+            "{operators:?} {target} {operands:?}"
+        );
+    }
+
+    #[test]
+    fn solves_the_example() {
+        // Both orders of + and * work, the search undoes + first
+        assert_eq!(
+            solve(&PART_1, 3267, &[81, 40, 27]),
+            Some(vec![Operator::Mul, Operator::Add])
+        );
+        assert_eq!(
+            render(
+                &[6, 8, 6, 15],
+                &solve(&PART_2, 7290, &[6, 8, 6, 15]).unwrap()
+            ),
+            "6 * 8 || 6 * 15"
+        );
+        assert_eq!(solve(&PART_2, 83, &[17, 5]), None);
+    }
+
+    #[test]
+    fn zero_never_divides() {
+        assert_eq!(solve(&[Operator::Div], 5, &[0, 0]), None);
+        assert_eq!(solve(&[Operator::Div], 0, &[0, 0]), None);
+        assert_eq!(
+            solve(&[Operator::Div], 0, &[0, 3]),
+            Some(vec![Operator::Div])
+        );
+    }
+
+    #[test]
+    fn multiplying_by_zero_ignores_what_came_before() {
+        check(&[Operator::Sub, Operator::Mul], 0, &[1, 2, 0]);
+        check(&[Operator::Div, Operator::Mul], 0, &[1, 0, 0]);
+        check(&[Operator::Sub, Operator::Mul], 0, &[1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        // A linear congruential generator, to vary the equations without a dependency
+        let mut state: u64 = 2024;
+        let mut next = |below: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % below
+        };
+        let subsets: Vec<Vec<Operator>> = (1..1 << ALL.len())
+            .map(|mask: usize| {
+                ALL.iter()
+                    .enumerate()
+                    .filter(|(idx, _)| mask & (1 << idx) != 0)
+                    .map(|(_, op)| *op)
+                    .collect()
+            })
+            .collect();
+        for operators in &subsets {
+            for _ in 0..40 {
+                let len = 1 + next(4) as usize;
+                let operands: Vec<u64> = (0..len).map(|_| next(7)).collect();
+                // Half the time, a target that some choice of operators reaches
+                let target = match next(2) {
+                    0 => next(50),
+                    _ => {
+                        let chosen: Vec<_> = (1..len)
+                            .map(|_| operators[next(operators.len() as u64) as usize])
+                            .collect();
+                        evaluate(&operands, &chosen).unwrap_or(0)
+                    }
+                };
+                check(operators, target, &operands);
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
+use equation::{PART_1, PART_2};
 use rayon::prelude::*;
 
+pub mod equation;
+
 /// Note on performance: The first iteration of this solution had a calculate_dfs function that accepted
 /// the number of possible operations (2 or 3) as a param.
 /// I replaced that function with specific versions calculate_dfs_2 and calculate_dfs_3 that hardcoded 2 and 3.
@@ -23,10 +26,15 @@ use rayon::prelude::*;
 /// In short: even though each loop of calculate_dfs is faster, all those extra loop cycles add up.
 ///
 /// The time to beat is now 193µs (-24.7%) and 5.2ms (-90.5%)
+///
+/// Third note on performance: `equation::solve` works backwards from the result instead. The last
+/// operand can only have been added if it's smaller than the result, multiplied if it divides the
+/// result, and concatenated if the result ends with it. Usually only one of those is possible,
+/// so the search barely branches. It also takes the operators as a list, so it isn't tied to 2 or 3.
 #[inline]
 pub fn part1(input: &str) -> u64 {
     parse(input)
-        .filter(|(result, operands)| calculate_bfs::<2>(*result, operands))
+        .filter(|(result, operands)| equation::solve(&PART_1, *result, operands).is_some())
         .map(|(result, _)| result)
         .sum()
 }
@@ -34,81 +42,11 @@ pub fn part1(input: &str) -> u64 {
 #[inline]
 pub fn part2(input: &str) -> u64 {
     parse(input)
-        .filter(|(result, operands)| calculate_bfs::<3>(*result, operands))
+        .filter(|(result, operands)| equation::solve(&PART_2, *result, operands).is_some())
         .map(|(result, _)| result)
         .sum()
 }
 
-fn calculate_bfs<const N: usize>(result: u64, operands: &[u64]) -> bool {
-    let mut intermediates: Vec<u64> = vec![operands[0]];
-    for operand in operands.iter().skip(1) {
-        let mut temp = Vec::with_capacity(intermediates.len() * N);
-        for n in intermediates {
-            let addition_result = n + operand;
-            if addition_result <= result {
-                temp.push(addition_result);
-            }
-            let multiplication_result = n * operand;
-            if multiplication_result <= result {
-                temp.push(multiplication_result);
-            }
-            if N == 3 {
-                let concat_result = (n * next_power_of_10(*operand)) + *operand;
-                if concat_result <= result {
-                    temp.push(concat_result);
-                }
-            }
-        }
-        intermediates = temp
-    }
-
-    intermediates.contains(&result)
-}
-
-#[allow(dead_code)]
-fn calculate_dfs<const N: u64>(result: u64, operands: &[u64]) -> bool {
-    // If there are 11 operands
-    // There will be 10 operations
-    let operations_len = operands.len() - 1;
-    // And N^10 iterations to calculate every possibile combination of operations
-    let num_iterations = N.pow(operations_len as u32);
-
-    for i in 0..num_iterations {
-        let mut calculated_result = operands[0];
-        let mut operations = i;
-        for j in 0..operations_len {
-            let operand = operands[j + 1];
-            calculated_result = match operations % N {
-                0 => calculated_result * operand,
-                1 => calculated_result + operand,
-                // Formatting takes 292ms while multiplication + addition takes 61ms (80% faster)
-                // format!("{calculated_result}{operand}").parse().unwrap()
-                2 => (calculated_result * next_power_of_10(operand)) + operand,
-                _ => unreachable!(),
-            };
-            if calculated_result > result {
-                break;
-            }
-            operations /= N;
-        }
-        if calculated_result == result {
-            return true;
-        }
-    }
-    false
-}
-
-fn next_power_of_10(n: u64) -> u64 {
-    if n == 0 {
-        return 10;
-    }
-    let mut power = 1;
-    while power <= n {
-        power *= 10;
-    }
-    power
-}
-
 pub fn parse(
     input: &str,
 ) -> impl rayon::prelude::ParallelIterator<Item = (u64, Vec<u64>)> + use<'_> {
@@ -0,0 +1,256 @@
+use std::fmt;
+
+/// The disk laid out block by block, each block holding a file id or free.
+/// Much slower than the checksums computed straight from the disk map, but it shows its work.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disk {
+    blocks: Vec<Option<usize>>,
+    // Start and length of each file, indexed by id
+    files: Vec<(usize, usize)>,
+}
+
+/// How files are moved into free space, from the end of the disk towards the start.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Strategy {
+    /// Part 1: one block at a time into the leftmost free block, splitting files up.
+    Blocks,
+    /// Part 2: each file once, in decreasing id order, whole into the leftmost free span that fits.
+    Files,
+}
+
+/// `len` blocks of file `id` moved from `from` to `to`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Move {
+    pub id: usize,
+    pub from: usize,
+    pub to: usize,
+    pub len: usize,
+}
+
+impl Disk {
+    /// `map` alternates file lengths and free space lengths, starting with a file.
+    pub fn new(map: &[usize]) -> Disk {
+        let mut blocks = Vec::with_capacity(map.iter().sum());
+        let mut files = Vec::with_capacity(map.len() / 2 + 1);
+        for (idx, len) in map.iter().enumerate() {
+            if idx % 2 == 0 {
+                files.push((blocks.len(), *len));
+                blocks.extend(std::iter::repeat_n(Some(idx / 2), *len));
+            } else {
+                blocks.extend(std::iter::repeat_n(None, *len));
+            }
+        }
+        Disk { blocks, files }
+    }
+
+    pub fn blocks(&self) -> &[Option<usize>] {
+        &self.blocks
+    }
+
+    /// Moves files until the strategy is done, one move per step.
+    pub fn compaction(&mut self, strategy: Strategy) -> Compaction<'_> {
+        let cursor = match strategy {
+            Strategy::Blocks => Cursor::Blocks {
+                free: 0,
+                used: self.blocks.len(),
+            },
+            Strategy::Files => Cursor::Files {
+                next_id: self.files.len(),
+                search_from: Vec::new(),
+            },
+        };
+        Compaction { disk: self, cursor }
+    }
+
+    pub fn compact(&mut self, strategy: Strategy) {
+        self.compaction(strategy).for_each(drop);
+    }
+
+    pub fn checksum(&self) -> usize {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, id)| id.map(|id| idx * id))
+            .sum()
+    }
+
+    fn move_blocks(&mut self, id: usize, from: usize, to: usize, len: usize) -> Move {
+        for offset in 0..len {
+            self.blocks[to + offset] = self.blocks[from + offset].take();
+        }
+        Move { id, from, to, len }
+    }
+}
+
+/// Renders the disk like the puzzle does, `00...111...2`. Ids above 9 don't fit in one
+/// character and are drawn as `#`.
+impl fmt::Display for Disk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for block in &self.blocks {
+            let c = match block {
+                None => '.',
+                Some(id) => u32::try_from(*id)
+                    .ok()
+                    .and_then(|id| char::from_digit(id, 10))
+                    .unwrap_or('#'),
+            };
+            write!(f, "{c}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The moves made while compacting a disk, each applied as it's returned.
+pub struct Compaction<'a> {
+    disk: &'a mut Disk,
+    cursor: Cursor,
+}
+
+enum Cursor {
+    // Every block before `free` is used, and every block from `used` onwards is free
+    Blocks {
+        free: usize,
+        used: usize,
+    },
+    // Files at or above `next_id` have had their turn. No span of free blocks long enough for a
+    // file of length `len` starts before `search_from[len]`. Spans before a file only ever shrink,
+    // so that holds for every file still to come.
+    Files {
+        next_id: usize,
+        search_from: Vec<usize>,
+    },
+}
+
+impl Iterator for Compaction<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let blocks = &self.disk.blocks;
+        match &mut self.cursor {
+            Cursor::Blocks { free, used } => {
+                while *free < *used && blocks[*free].is_some() {
+                    *free += 1;
+                }
+                while *used > *free && blocks[*used - 1].is_none() {
+                    *used -= 1;
+                }
+                if *free >= *used {
+                    return None;
+                }
+                *used -= 1;
+                let id = blocks[*used].expect("the block is used");
+                Some(self.disk.move_blocks(id, *used, *free, 1))
+            }
+            Cursor::Files {
+                next_id,
+                search_from,
+            } => loop {
+                *next_id = next_id.checked_sub(1)?;
+                let id = *next_id;
+                let (start, len) = self.disk.files[id];
+                if len == 0 {
+                    continue;
+                }
+                if search_from.len() <= len {
+                    search_from.resize(len + 1, 0);
+                }
+                let (mut span_start, mut position) = (search_from[len], search_from[len]);
+                while position < start && position - span_start < len {
+                    if self.disk.blocks[position].is_some() {
+                        span_start = position + 1;
+                    }
+                    position += 1;
+                }
+                search_from[len] = span_start;
+                if position - span_start < len {
+                    continue;
+                }
+                self.disk.files[id] = (span_start, len);
+                return Some(self.disk.move_blocks(id, start, span_start, len));
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_INPUT: &str = include_str!("test-input.txt");
+    static FULL_INPUT: &str = include_str!("input.txt");
+
+    fn checksum(map: &[usize], strategy: Strategy) -> usize {
+        let mut disk = Disk::new(map);
+        disk.compact(strategy);
+        disk.checksum()
+    }
+
+    #[test]
+    fn renders_the_example() {
+        let map = super::super::parse(TEST_INPUT);
+        let mut disk = Disk::new(&map);
+        assert_eq!(
+            disk.to_string(),
+            "00...111...2...333.44.5555.6666.777.888899"
+        );
+
+        let mut blocks = disk.clone();
+        blocks.compact(Strategy::Blocks);
+        assert_eq!(
+            blocks.to_string(),
+            "0099811188827773336446555566.............."
+        );
+
+        let moves: Vec<_> = disk.compaction(Strategy::Files).collect();
+        assert_eq!(
+            moves[0],
+            Move {
+                id: 9,
+                from: 40,
+                to: 2,
+                len: 2
+            }
+        );
+        assert_eq!(
+            disk.to_string(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+    }
+
+    #[test]
+    fn simulation_matches_checksums() {
+        for input in [TEST_INPUT, FULL_INPUT] {
+            let map = super::super::parse(input);
+            assert_eq!(checksum(&map, Strategy::Blocks), super::super::part1(input));
+            assert_eq!(checksum(&map, Strategy::Files), super::super::part2(input));
+        }
+    }
+
+    #[test]
+    fn simulation_matches_checksums_on_generated_maps() {
+        // A linear congruential generator, to vary the maps without a dependency
+        let mut state: u64 = 2024;
+        let mut digit = |low: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (low + (state >> 33) % (10 - low)) as usize
+        };
+        for len in (1..200).step_by(2) {
+            let map: Vec<usize> = (0..len)
+                .map(|idx| if idx % 2 == 0 { digit(1) } else { digit(0) })
+                .collect();
+            let input: String = map.iter().map(|len| len.to_string()).collect();
+            assert_eq!(
+                checksum(&map, Strategy::Blocks),
+                super::super::part1(&input),
+                "{input}"
+            );
+            assert_eq!(
+                checksum(&map, Strategy::Files),
+                super::super::part2(&input),
+                "{input}"
+            );
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use std::{cmp::Ordering, collections::BinaryHeap};
 
+pub mod disk;
+
 /// Performance.
 /// Probably my biggest win by far - 98.7% reduction for part 2: 27.7ms -> 364.33 µs
 /// Most of the time was spent scanning the free space array to find one that fit the criteria
@@ -0,0 +1 @@
+common::solutions!(2025;);